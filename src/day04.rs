@@ -1,7 +1,8 @@
+use crate::grid::{Direction, Grid, Point};
 use crate::puzzle::Puzzle;
 
 pub struct Day {
-    grid: Vec<Vec<char>>,
+    grid: Grid<char>,
 }
 
 impl Puzzle for Day {
@@ -11,13 +12,11 @@ impl Puzzle for Day {
     /// Time complexity: O(n*m)
     /// Auxiliary space complexity: O(1)
     fn solve_part_1(&self) -> String {
-        let mut count = 0;
-        for row in 0..self.grid.len() {
-            for col in 0..self.grid[0].len() {
-                count += count_xmas(&self.grid, row, col);
-            }
-        }
-        count.to_string()
+        self.grid
+            .points()
+            .map(|point| count_xmas(&self.grid, point))
+            .sum::<usize>()
+            .to_string()
     }
 
     /// We're given a grid of characters and asked to count the number of occurrences of a
@@ -26,75 +25,51 @@ impl Puzzle for Day {
     /// Time complexity: O(n*m)
     /// Auxiliary space complexity: O(1)
     fn solve_part_2(&self) -> String {
-        let mut count = 0;
-        for row in 0..self.grid.len() {
-            for col in 0..self.grid[0].len() {
-                if is_x_mas(&self.grid, row as isize, col as isize) {
-                    count += 1;
-                }
-            }
-        }
-        count.to_string()
+        self.grid
+            .points()
+            .filter(|&point| is_x_mas(&self.grid, point))
+            .count()
+            .to_string()
     }
 }
 
-fn count_xmas(grid: &[Vec<char>], row: usize, col: usize) -> i32 {
-    const DIRECTIONS: [(i8, i8); 8] = [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-    DIRECTIONS
-        .iter()
-        .filter(|&&dir| is_xmas(grid, row as isize, col as isize, dir))
-        .count() as i32
-}
-
-fn is_xmas(grid: &[Vec<char>], mut row: isize, mut col: isize, dir: (i8, i8)) -> bool {
-    const XMAS: &str = "XMAS";
-    for expected in XMAS.chars() {
-        if !in_bounds(grid, row, col) || grid[row as usize][col as usize] != expected {
-            return false;
-        }
-        row += dir.0 as isize;
-        col += dir.1 as isize;
+/// Counts the XMASes that start at the point. Each neighbor holding an `M` fixes the direction in
+/// which the rest of the word has to continue.
+fn count_xmas(grid: &Grid<char>, point: Point) -> usize {
+    if grid[point] != 'X' {
+        return 0;
     }
-    true
+    grid.neighbors8(point)
+        .filter(|&next| grid[next] == 'M')
+        .filter(|&next| {
+            let (drow, dcol) = (next.row - point.row, next.col - point.col);
+            (2..).zip("AS".chars()).all(|(distance, expected)| {
+                let point = Point::new(point.row + distance * drow, point.col + distance * dcol);
+                grid.get(point) == Some(&expected)
+            })
+        })
+        .count()
 }
 
-fn is_x_mas(grid: &[Vec<char>], row: isize, col: isize) -> bool {
-    if grid[row as usize][col as usize] != 'A' {
+fn is_x_mas(grid: &Grid<char>, point: Point) -> bool {
+    if grid[point] != 'A' {
         return false;
     }
     let diagonals = [
-        ((row - 1, col - 1), (row + 1, col + 1)),
-        ((row - 1, col + 1), (row + 1, col - 1)),
+        (Direction::NorthWest, Direction::SouthEast),
+        (Direction::NorthEast, Direction::SouthWest),
     ];
-    diagonals.iter().all(|&(loc1, loc2)| {
-        if !in_bounds(grid, loc1.0, loc1.1) || !in_bounds(grid, loc2.0, loc2.1) {
-            return false;
-        }
-        let val1 = grid[loc1.0 as usize][loc1.1 as usize];
-        let val2 = grid[loc2.0 as usize][loc2.1 as usize];
-        (val1 == 'M' && val2 == 'S') || (val1 == 'S' && val2 == 'M')
+    diagonals.iter().all(|&(dir1, dir2)| {
+        matches!(
+            (grid.get(point.step(dir1)), grid.get(point.step(dir2))),
+            (Some('M'), Some('S')) | (Some('S'), Some('M'))
+        )
     })
 }
 
-fn in_bounds(grid: &[Vec<char>], row: isize, col: isize) -> bool {
-    row >= 0 && (row as usize) < grid.len() && col >= 0 && (col as usize) < grid[0].len()
-}
-
 impl Day {
     pub fn create(input: &str) -> Box<dyn Puzzle> {
-        let grid = input
-            .lines()
-            .map(|line| line.trim().chars().collect())
-            .collect();
+        let grid = Grid::parse(input);
         Box::new(Day { grid })
     }
 }
@@ -1,51 +1,49 @@
-use crate::puzzle::Puzzle;
+use crate::puzzle::{Puzzle, PuzzleParams};
+use itertools::Itertools;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
 
 pub struct Day {
     equations: Vec<Equation>,
+    /// The operators the `equations` mode solves with.
+    operators: OperatorSet,
 }
 
 impl Puzzle for Day {
-    /// TODO
+    /// Sums the test values of the equations that can be made true with addition and
+    /// multiplication.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(o^n) per equation in the worst case, where o is the number of operators
+    /// and n the number of operands, though undoing the operators prunes almost every branch
+    /// Auxiliary space complexity: O(n)
     fn solve_part_1(&self) -> String {
-        let mut sum = 0;
-        for equation in &self.equations {
-            if test_equation(
-                equation.operands[0],
-                equation.test_value,
-                &equation.operands[1..],
-                false,
-            ) {
-                sum += equation.test_value;
-            }
-        }
-        sum.to_string()
+        self.calibration_result(PART_1_OPERATORS).to_string()
     }
 
-    /// TODO
+    /// Sums the test values of the equations that can be made true with addition, multiplication
+    /// and concatenation.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(o^n) per equation in the worst case, where o is the number of operators
+    /// and n the number of operands, though undoing the operators prunes almost every branch
+    /// Auxiliary space complexity: O(n)
     fn solve_part_2(&self) -> String {
-        let mut sum = 0;
-        for equation in &self.equations {
-            if test_equation(
-                equation.operands[0],
-                equation.test_value,
-                &equation.operands[1..],
-                true,
-            ) {
-                sum += equation.test_value;
-            }
+        self.calibration_result(PART_2_OPERATORS).to_string()
+    }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        match mode {
+            "equations" => Some(self.report(&self.operators.0)),
+            _ => None,
         }
-        sum.to_string()
     }
 }
 
+const PART_1_OPERATORS: &[Operator] = &[Operator::Add, Operator::Multiply];
+const PART_2_OPERATORS: &[Operator] = &[Operator::Add, Operator::Multiply, Operator::Concatenate];
+
 impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
+    pub fn create(input: &str, params: &PuzzleParams) -> Box<dyn Puzzle> {
         let equations = input
             .lines()
             .map(|line| {
@@ -63,7 +61,44 @@ impl Day {
                 }
             })
             .collect();
-        Box::new(Day { equations })
+        let operators = params.get("operators", OperatorSet(PART_2_OPERATORS.to_vec()));
+        Box::new(Day {
+            equations,
+            operators,
+        })
+    }
+
+    fn calibration_result(&self, operators: &[Operator]) -> u64 {
+        self.equations
+            .iter()
+            .filter(|equation| equation.solve(operators).is_some())
+            .map(|equation| equation.test_value)
+            .sum()
+    }
+
+    /// Lists every equation with the operators that make it true, if there are any, followed by
+    /// the total calibration result.
+    fn report(&self, operators: &[Operator]) -> String {
+        let mut output = String::new();
+        for equation in &self.equations {
+            match equation.solve(operators) {
+                Some(assignment) => writeln!(
+                    output,
+                    "{} = {}",
+                    equation.test_value,
+                    equation.format_with(&assignment)
+                ),
+                None => writeln!(output, "{} can't be made true", equation),
+            }
+            .unwrap();
+        }
+        write!(
+            output,
+            "Total calibration result: {}",
+            self.calibration_result(operators)
+        )
+        .unwrap();
+        output
     }
 }
 
@@ -72,22 +107,192 @@ struct Equation {
     operands: Vec<u64>,
 }
 
-fn test_equation(current: u64, expected: u64, remainder: &[u64], part_2: bool) -> bool {
-    if remainder.is_empty() {
-        return current == expected;
+impl Equation {
+    /// Returns the operators to place between the operands (always evaluated left to right) that
+    /// make the equation true, or None if no choice of the given operators does.
+    fn solve(&self, operators: &[Operator]) -> Option<Vec<Operator>> {
+        solve_backwards(self.test_value, &self.operands, operators)
     }
-    if current > expected {
-        return false;
+
+    /// Writes out the operands with the given operators between them.
+    fn format_with(&self, assignment: &[Operator]) -> String {
+        let mut output = self.operands[0].to_string();
+        for (operator, operand) in assignment.iter().zip(&self.operands[1..]) {
+            write!(output, " {} {}", operator, operand).unwrap();
+        }
+        output
     }
-    test_equation(current + remainder[0], expected, &remainder[1..], part_2)
-        || test_equation(current * remainder[0], expected, &remainder[1..], part_2)
-        || (part_2
-            && test_equation(
-                format!("{}{}", current, remainder[0]).parse().unwrap(),
-                expected,
-                &remainder[1..],
-                part_2,
-            ))
+}
+
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.test_value, self.operands.iter().join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Multiply,
+    Concatenate,
+    Subtract,
+    Power,
+}
+
+/// The left operands that an operator could have been applied to, given its right operand and
+/// its result.
+enum Inverse {
+    None,
+    One(u64),
+    /// Every left operand gives the same result, e.g. when multiplying by zero.
+    Any,
+}
+
+impl Operator {
+    /// Applies the operator, or returns None if the result would overflow or be negative.
+    fn apply(self, left: u64, right: u64) -> Option<u64> {
+        match self {
+            Operator::Add => left.checked_add(right),
+            Operator::Multiply => left.checked_mul(right),
+            Operator::Concatenate => left
+                .checked_mul(10u64.checked_pow(digits(right))?)?
+                .checked_add(right),
+            Operator::Subtract => left.checked_sub(right),
+            Operator::Power => left.checked_pow(right.try_into().ok()?),
+        }
+    }
+
+    /// Finds the left operands that the operator turns into the result with the given right
+    /// operand, undoing it: subtraction undoes addition, division undoes multiplication,
+    /// stripping the right operand's digits off the end of the result undoes concatenation, and
+    /// taking the integer root undoes raising to a power.
+    fn undo(self, result: u64, right: u64) -> Inverse {
+        let left = match self {
+            Operator::Add => result.checked_sub(right),
+            Operator::Multiply if right == 0 => {
+                return if result == 0 {
+                    Inverse::Any
+                } else {
+                    Inverse::None
+                };
+            }
+            Operator::Multiply => result.is_multiple_of(right).then(|| result / right),
+            Operator::Concatenate => {
+                // Nothing concatenates with a right operand too long to shift past.
+                let Some(shift) = 10u64.checked_pow(digits(right)) else {
+                    return Inverse::None;
+                };
+                (result % shift == right).then(|| result / shift)
+            }
+            Operator::Subtract => result.checked_add(right),
+            Operator::Power if right == 0 => {
+                return if result == 1 {
+                    Inverse::Any
+                } else {
+                    Inverse::None
+                };
+            }
+            Operator::Power => {
+                // Powers grow with their base, so the exact integer root can be binary searched
+                // for, treating bases whose power overflows as too big.
+                let (mut low, mut high) = (0, result);
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    match self.apply(mid, right) {
+                        Some(power) if power < result => low = mid + 1,
+                        _ => high = mid,
+                    }
+                }
+                (self.apply(low, right) == Some(result)).then_some(low)
+            }
+        };
+        match left {
+            Some(left) => Inverse::One(left),
+            None => Inverse::None,
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Operator::Add => "+",
+            Operator::Multiply => "*",
+            Operator::Concatenate => "||",
+            Operator::Subtract => "-",
+            Operator::Power => "^",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Parses an operator from its symbol or its name, e.g. `*` or `multiply`.
+impl FromStr for Operator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "+" | "add" => Ok(Operator::Add),
+            "*" | "multiply" => Ok(Operator::Multiply),
+            "||" | "concatenate" => Ok(Operator::Concatenate),
+            "-" | "subtract" => Ok(Operator::Subtract),
+            "^" | "power" => Ok(Operator::Power),
+            _ => Err(format!("Unknown operator: {}", s)),
+        }
+    }
+}
+
+/// A comma-separated list of operators, e.g. `+,*,||`.
+#[derive(Debug, PartialEq, Eq)]
+struct OperatorSet(Vec<Operator>);
+
+impl FromStr for OperatorSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(|operator| operator.trim().parse())
+            .collect::<Result<_, _>>()
+            .map(OperatorSet)
+    }
+}
+
+/// Returns the number of decimal digits in the number.
+fn digits(n: u64) -> u32 {
+    n.checked_ilog10().unwrap_or(0) + 1
+}
+
+/// Finds operators that turn the operands into the target by undoing the last operator first.
+/// Most operators can only be undone in at most one way (a product must be divisible by its right
+/// operand, a concatenation must end with it, a sum must be at least it), so the search is pruned
+/// as soon as the target can no longer be reached.
+fn solve_backwards(target: u64, operands: &[u64], operators: &[Operator]) -> Option<Vec<Operator>> {
+    let (&last, rest) = operands.split_last()?;
+    if rest.is_empty() {
+        return (last == target).then(Vec::new);
+    }
+    operators.iter().find_map(|&operator| {
+        let mut assignment = match operator.undo(target, last) {
+            Inverse::None => None,
+            Inverse::One(left) => solve_backwards(left, rest, operators),
+            Inverse::Any => evaluate_any(rest[0], &rest[1..], operators),
+        }?;
+        assignment.push(operator);
+        Some(assignment)
+    })
+}
+
+/// Finds any operators that can be applied to the operands without overflowing or going negative,
+/// for when it doesn't matter what they evaluate to.
+fn evaluate_any(current: u64, remainder: &[u64], operators: &[Operator]) -> Option<Vec<Operator>> {
+    let Some((&next, rest)) = remainder.split_first() else {
+        return Some(Vec::new());
+    };
+    operators.iter().find_map(|&operator| {
+        let mut assignment = evaluate_any(operator.apply(current, next)?, rest, operators)?;
+        assignment.insert(0, operator);
+        Some(assignment)
+    })
 }
 
 #[cfg(test)]
@@ -107,14 +312,195 @@ mod tests {
             192: 17 8 14\n\
             21037: 9 7 18 13\n\
             292: 11 6 16 20";
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "3749");
     }
 
+    #[test]
+    fn test_report_example_1() {
+        let input = "\
+            190: 10 19\n\
+            3267: 81 40 27\n\
+            83: 17 5\n\
+            156: 15 6\n\
+            7290: 6 8 6 15\n\
+            161011: 16 10 13\n\
+            192: 17 8 14\n\
+            21037: 9 7 18 13\n\
+            292: 11 6 16 20";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(
+            puzzle.inspect("equations").unwrap(),
+            "\
+            190 = 10 * 19\n\
+            3267 = 81 * 40 + 27\n\
+            83: 17 5 can't be made true\n\
+            156 = 15 || 6\n\
+            7290 = 6 * 8 || 6 * 15\n\
+            161011: 16 10 13 can't be made true\n\
+            192 = 17 || 8 + 14\n\
+            21037: 9 7 18 13 can't be made true\n\
+            292 = 11 + 6 * 16 + 20\n\
+            Total calibration result: 11387"
+        );
+        let params = PuzzleParams::default().with("operators", "-, ^");
+        let puzzle = Day::create("2: 5 3\n125: 5 3\n4: 5 3 2\n7: 5 3", &params);
+        assert_eq!(
+            puzzle.inspect("equations").unwrap(),
+            "\
+            2 = 5 - 3\n\
+            125 = 5 ^ 3\n\
+            4 = 5 - 3 ^ 2\n\
+            7: 5 3 can't be made true\n\
+            Total calibration result: 131"
+        );
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        assert_eq!(
+            "+,multiply, ||".parse(),
+            Ok(OperatorSet(vec![
+                Operator::Add,
+                Operator::Multiply,
+                Operator::Concatenate
+            ]))
+        );
+        assert_eq!(
+            "+,/".parse::<OperatorSet>(),
+            Err("Unknown operator: /".to_string())
+        );
+    }
+
+    #[test]
+    fn test_undo_zero_operands() {
+        // Multiplying by zero gives zero whatever came before, as long as that can be evaluated.
+        let operators = [Operator::Subtract, Operator::Multiply];
+        assert_eq!(
+            solve_backwards(0, &[1, 2, 0], &operators),
+            Some(vec![Operator::Multiply, Operator::Multiply])
+        );
+        assert_eq!(
+            solve_backwards(0, &[1, 2, 0], &[Operator::Multiply])
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(solve_backwards(0, &[1, 2, 0], &[Operator::Subtract]), None);
+        assert_eq!(
+            solve_backwards(1, &[7, 0], &[Operator::Power]),
+            Some(vec![Operator::Power])
+        );
+    }
+
+    #[test]
+    fn test_undo_large_operands() {
+        let power = [Operator::Power];
+        let big = 100_000_000_000_000_003;
+        assert_eq!(
+            solve_backwards(big, &[big, 1], &power),
+            Some(vec![Operator::Power])
+        );
+        let square = 4_294_967_295u64.pow(2);
+        assert_eq!(
+            solve_backwards(square, &[4_294_967_295, 2], &power),
+            Some(vec![Operator::Power])
+        );
+        assert_eq!(
+            solve_backwards(square - 1, &[4_294_967_294, 2], &power),
+            None
+        );
+        assert_eq!(
+            solve_backwards(3u64.pow(40), &[3, 40], &power),
+            Some(vec![Operator::Power])
+        );
+        assert_eq!(
+            solve_backwards(u64::MAX, &[u64::MAX, 1], &power),
+            Some(vec![Operator::Power])
+        );
+        // A right operand of 20 digits can't be concatenated onto anything without overflowing.
+        let concatenate = [Operator::Concatenate];
+        let long = 10_000_000_000_000_000_000;
+        assert_eq!(solve_backwards(long, &[0, long], &concatenate), None);
+        assert_eq!(
+            solve_backwards(u64::MAX, &[1, u64::MAX], &concatenate),
+            None
+        );
+    }
+
+    /// Tries every assignment of operators, evaluating each one forwards.
+    fn solvable_by_brute_force(target: u64, operands: &[u64], operators: &[Operator]) -> bool {
+        itertools::repeat_n(operators.iter().copied(), operands.len() - 1)
+            .multi_cartesian_product()
+            .any(|assignment| evaluate(operands, &assignment) == Some(target))
+    }
+
+    fn evaluate(operands: &[u64], assignment: &[Operator]) -> Option<u64> {
+        assignment
+            .iter()
+            .zip(&operands[1..])
+            .try_fold(operands[0], |left, (operator, &right)| {
+                operator.apply(left, right)
+            })
+    }
+
+    #[test]
+    fn test_solve_backwards_matches_brute_force() {
+        use rand::seq::IndexedRandom;
+        use rand::{Rng, SeedableRng};
+        let all = [
+            Operator::Add,
+            Operator::Multiply,
+            Operator::Concatenate,
+            Operator::Subtract,
+            Operator::Power,
+        ];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _ in 0..2000 {
+            let operators = all
+                .iter()
+                .copied()
+                .filter(|_| rng.random_bool(0.6))
+                .collect::<Vec<_>>();
+            let operands = (0..rng.random_range(1..6))
+                .map(|_| rng.random_range(0..6))
+                .collect::<Vec<_>>();
+            // Build most targets from a random assignment so that plenty of equations are solvable.
+            let target = if operators.is_empty() || rng.random_bool(0.2) {
+                rng.random_range(0..100)
+            } else {
+                let assignment = (1..operands.len())
+                    .map(|_| *operators.choose(&mut rng).unwrap())
+                    .collect::<Vec<_>>();
+                match evaluate(&operands, &assignment) {
+                    Some(target) => target,
+                    None => continue,
+                }
+            };
+            let solution = solve_backwards(target, &operands, &operators);
+            assert_eq!(
+                solution.is_some(),
+                solvable_by_brute_force(target, &operands, &operators),
+                "{}: {:?} with {:?}",
+                target,
+                operands,
+                operators
+            );
+            if let Some(assignment) = solution {
+                assert!(
+                    assignment
+                        .iter()
+                        .all(|operator| operators.contains(operator))
+                );
+                assert_eq!(evaluate(&operands, &assignment), Some(target));
+            }
+        }
+    }
+
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/07")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "20665830408335");
     }
 
@@ -130,14 +516,14 @@ mod tests {
             192: 17 8 14\n\
             21037: 9 7 18 13\n\
             292: 11 6 16 20";
-        let puzzle = Day::create(input);
+        let puzzle = Day::create(input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "11387");
     }
 
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/07")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "354060705047464");
     }
 }
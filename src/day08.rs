@@ -1,194 +1,188 @@
+use crate::grid::{Grid, Point};
+use crate::number_theory::gcd;
 use crate::puzzle::Puzzle;
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::num::NonZeroU32;
 
 pub struct Day {
     antennas: HashMap<char, Vec<Point>>,
-    height: i32,
-    width: i32,
+    grid: Grid<char>,
 }
 
 impl Puzzle for Day {
-    /// TODO
+    /// We're given a map of antennas and asked to count the locations that are in line with two
+    /// antennas of the same frequency, and twice as far from one of them as the other.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(a^2) where a is the number of antennas
+    /// Auxiliary space complexity: O(a^2)
     fn solve_part_1(&self) -> String {
-        self.antennas
-            .iter()
-            .flat_map(|(_, antennas)| {
-                antennas
-                    .iter()
-                    .combinations(2)
-                    .flat_map(|pair| find_antinode_pair(pair[0], pair[1]))
-                    .collect::<Vec<Point>>()
-            })
-            .filter(|antinode: &Point| {
-                antinode.row >= 0
-                    && antinode.row < self.height
-                    && antinode.col >= 0
-                    && antinode.col < self.width
-            })
-            .sorted_unstable()
-            .dedup()
-            .count()
-            .to_string()
+        self.count_antinodes(PART_1_RULE).to_string()
     }
 
-    /// TODO
+    /// We're asked to count the locations that are in line with two antennas of the same frequency
+    /// at any distance.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(a^2*(w+h)) where a is the number of antennas
+    /// Auxiliary space complexity: O(a^2*(w+h))
     fn solve_part_2(&self) -> String {
+        self.count_antinodes(AntinodeRule::Collinear).to_string()
+    }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        match mode {
+            "antinodes" => Some(self.report(PART_1_RULE)),
+            "harmonics" => Some(self.report(AntinodeRule::Collinear)),
+            _ => None,
+        }
+    }
+}
+
+const PART_1_RULE: AntinodeRule = AntinodeRule::Ratio {
+    ratio: NonZeroU32::new(2).unwrap(),
+    include_between: false,
+};
+
+/// Which of the points on the line through a pair of antennas are antinodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AntinodeRule {
+    /// The points that are `ratio` times as far from one antenna as from the other. Those outside
+    /// the pair are always included, and those between them only if `include_between`.
+    Ratio {
+        ratio: NonZeroU32,
+        include_between: bool,
+    },
+    /// Every grid point in line with the antennas.
+    Collinear,
+}
+
+impl Day {
+    pub fn create(input: &str) -> Box<dyn Puzzle> {
+        let grid = Grid::parse(input);
+        let mut antennas = HashMap::new();
+        for (point, &c) in grid.iter() {
+            if c != '.' {
+                antennas.entry(c).or_insert_with(Vec::new).push(point);
+            }
+        }
+        Box::new(Day { antennas, grid })
+    }
+
+    /// Returns the antinodes within the grid, grouped by the frequency of the antennas that
+    /// create them.
+    fn antinodes_by_frequency(&self, rule: AntinodeRule) -> BTreeMap<char, BTreeSet<Point>> {
         self.antennas
             .iter()
-            .flat_map(|(_, antennas)| {
-                antennas
+            .map(|(&frequency, antennas)| {
+                let antinodes = antennas
                     .iter()
-                    .combinations(2)
-                    .flat_map(|pair| {
-                        find_all_antinode_pairs(pair[0], pair[1], self.height, self.width)
-                    })
-                    .collect::<Vec<Point>>()
-            })
-            .filter(|antinode: &Point| {
-                antinode.row >= 0
-                    && antinode.row < self.height
-                    && antinode.col >= 0
-                    && antinode.col < self.width
+                    .tuple_combinations()
+                    .flat_map(|(&a, &b)| find_antinodes(a, b, rule, &self.grid))
+                    .collect();
+                (frequency, antinodes)
             })
-            .sorted_unstable()
-            .dedup()
+            .collect()
+    }
+
+    fn count_antinodes(&self, rule: AntinodeRule) -> usize {
+        self.antinodes_by_frequency(rule)
+            .values()
+            .flatten()
+            .unique()
             .count()
-            .to_string()
     }
-}
 
-impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
-        let mut height = 0;
-        let mut width = 0;
-        let mut antennas = HashMap::new();
-        for (row, line) in input.lines().enumerate() {
-            for (col, c) in line.chars().enumerate() {
-                if c != '.' {
-                    antennas.entry(c).or_insert_with(Vec::new).push(Point {
-                        row: row as i32,
-                        col: col as i32,
-                    });
+    /// Describes the antinodes: the map with a `#` on every antinode that isn't on an antenna, the
+    /// number of antinodes of each frequency, and the locations where frequencies overlap.
+    fn report(&self, rule: AntinodeRule) -> String {
+        let by_frequency = self.antinodes_by_frequency(rule);
+        let mut grid = self.grid.clone();
+        let mut frequencies: BTreeMap<Point, Vec<char>> = BTreeMap::new();
+        for (&frequency, antinodes) in &by_frequency {
+            for &antinode in antinodes {
+                if grid[antinode] == '.' {
+                    grid[antinode] = '#';
                 }
-                width = width.max(col as i32 + 1)
+                frequencies.entry(antinode).or_default().push(frequency);
             }
-            height = height.max(row as i32 + 1);
         }
 
-        Box::new(Day {
-            antennas,
-            height,
-            width,
-        })
+        let mut output = format!("{}\n", grid);
+        writeln!(output, "\n{} unique antinodes", frequencies.len()).unwrap();
+        for (frequency, antinodes) in &by_frequency {
+            writeln!(
+                output,
+                "Frequency {}: {} antinodes",
+                frequency,
+                antinodes.len()
+            )
+            .unwrap();
+        }
+        let overlaps = frequencies
+            .iter()
+            .filter(|(_, frequencies)| frequencies.len() > 1)
+            .collect_vec();
+        if overlaps.is_empty() {
+            writeln!(output, "No frequencies share an antinode").unwrap();
+        }
+        for (antinode, frequencies) in overlaps {
+            writeln!(
+                output,
+                "Frequencies {} share the antinode at ({}, {})",
+                frequencies.iter().join(", "),
+                antinode.row,
+                antinode.col
+            )
+            .unwrap();
+        }
+        output.trim_end().to_string()
     }
 }
 
-#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone)]
-struct Point {
-    row: i32,
-    col: i32,
-}
-
-fn find_all_antinode_pairs(
-    antenna_1: &Point,
-    antenna_2: &Point,
-    height: i32,
-    width: i32,
-) -> Vec<Point> {
-    if antenna_1.col == antenna_2.col {
-        // Vertical line.
-        return (0..height)
-            .map(|row| Point {
-                row,
-                col: antenna_1.col,
-            })
-            .collect();
-    }
-    if antenna_1.row == antenna_2.row {
-        // Horizontal line.
-        return (0..width)
-            .map(|col| Point {
-                row: antenna_1.row,
-                col,
-            })
-            .collect();
-    }
+/// Returns the antinodes of a pair of antennas that lie within the grid.
+fn find_antinodes(a: Point, b: Point, rule: AntinodeRule, grid: &Grid<char>) -> Vec<Point> {
+    let (rise, run) = (b.row - a.row, b.col - a.col);
     let mut antinodes = Vec::new();
-    let rise = antenna_2.row - antenna_1.row;
-    let run = antenna_2.col - antenna_1.col;
-    let mut point = Point {
-        row: antenna_1.row,
-        col: antenna_1.col,
-    };
-    while in_bounds(&point, height, width) {
-        antinodes.push(point.clone());
-        point.row += rise;
-        point.col += run;
-    }
-    point.row -= rise;
-    point.col -= run;
-    while in_bounds(&point, height, width) {
-        antinodes.push(point.clone());
-        point.row -= rise;
-        point.col -= run;
+    match rule {
+        AntinodeRule::Ratio {
+            ratio,
+            include_between,
+        } => {
+            let ratio = ratio.get() as i32;
+            // Outside the pair, the antinodes are 1/(ratio-1) of the gap beyond each antenna.
+            if ratio > 1 && rise % (ratio - 1) == 0 && run % (ratio - 1) == 0 {
+                let (drow, dcol) = (rise / (ratio - 1), run / (ratio - 1));
+                antinodes.push(Point::new(b.row + drow, b.col + dcol));
+                antinodes.push(Point::new(a.row - drow, a.col - dcol));
+            }
+            // Between the pair, they're 1/(ratio+1) of the gap in from each antenna.
+            if include_between && rise % (ratio + 1) == 0 && run % (ratio + 1) == 0 {
+                let (drow, dcol) = (rise / (ratio + 1), run / (ratio + 1));
+                antinodes.push(Point::new(a.row + drow, a.col + dcol));
+                antinodes.push(Point::new(b.row - drow, b.col - dcol));
+            }
+        }
+        AntinodeRule::Collinear => {
+            // Stepping by the reduced slope visits every grid point on the line.
+            let divisor = gcd(rise.into(), run.into()) as i32;
+            let (drow, dcol) = (rise / divisor, run / divisor);
+            let mut point = a;
+            while grid.in_bounds(point) {
+                antinodes.push(point);
+                point = Point::new(point.row + drow, point.col + dcol);
+            }
+            let mut point = Point::new(a.row - drow, a.col - dcol);
+            while grid.in_bounds(point) {
+                antinodes.push(point);
+                point = Point::new(point.row - drow, point.col - dcol);
+            }
+        }
     }
+    antinodes.retain(|&antinode| grid.in_bounds(antinode));
     antinodes
 }
 
-fn find_antinode_pair(antenna_1: &Point, antenna_2: &Point) -> Vec<Point> {
-    if antenna_1.col == antenna_2.col {
-        // Vertical line.
-        let x = std::cmp::min(antenna_1.row, antenna_2.row);
-        let y = std::cmp::max(antenna_1.row, antenna_2.row);
-        return vec![
-            Point {
-                row: 2 * y - x,
-                col: antenna_1.col,
-            },
-            Point {
-                row: 2 * x - y,
-                col: antenna_1.col,
-            },
-        ];
-    }
-    if antenna_1.col == antenna_2.col {
-        // Horizontal line.
-        let x = std::cmp::min(antenna_1.col, antenna_2.col);
-        let y = std::cmp::max(antenna_1.col, antenna_2.col);
-        return vec![
-            Point {
-                row: antenna_1.row,
-                col: 2 * y - x,
-            },
-            Point {
-                row: antenna_1.row,
-                col: 2 * x - y,
-            },
-        ];
-    }
-    vec![
-        Point {
-            row: 2 * antenna_2.row - antenna_1.row,
-            col: 2 * antenna_2.col - antenna_1.col,
-        },
-        Point {
-            row: 2 * antenna_1.row - antenna_2.row,
-            col: 2 * antenna_1.col - antenna_2.col,
-        },
-    ]
-}
-
-fn in_bounds(point: &Point, height: i32, width: i32) -> bool {
-    point.row >= 0 && point.row < height && point.col >= 0 && point.col < width
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -213,6 +207,77 @@ mod tests {
         assert_eq!(puzzle.solve_part_1(), "14");
     }
 
+    #[test]
+    fn test_report_example_1() {
+        let input = "\
+            ............\n\
+            ........0...\n\
+            .....0......\n\
+            .......0....\n\
+            ....0.......\n\
+            ......A.....\n\
+            ............\n\
+            ............\n\
+            ........A...\n\
+            .........A..\n\
+            ............\n\
+            ............";
+        let puzzle = Day::create(input);
+        assert_eq!(
+            puzzle.inspect("antinodes").unwrap(),
+            "\
+            ......#....#\n\
+            ...#....0...\n\
+            ....#0....#.\n\
+            ..#....0....\n\
+            ....0....#..\n\
+            .#....A.....\n\
+            ...#........\n\
+            #......#....\n\
+            ........A...\n\
+            .........A..\n\
+            ..........#.\n\
+            ..........#.\n\
+            \n\
+            14 unique antinodes\n\
+            Frequency 0: 10 antinodes\n\
+            Frequency A: 5 antinodes\n\
+            Frequencies 0, A share the antinode at (1, 3)"
+        );
+        assert_eq!(
+            puzzle.inspect("harmonics").unwrap(),
+            "\
+            ##....#....#\n\
+            .#.#....0...\n\
+            ..#.#0....#.\n\
+            ..##...0....\n\
+            ....0....#..\n\
+            .#...#A....#\n\
+            ...#..#.....\n\
+            #....#.#....\n\
+            ..#.....A...\n\
+            ....#....A..\n\
+            .#........#.\n\
+            ...#......##\n\
+            \n\
+            34 unique antinodes\n\
+            Frequency 0: 21 antinodes\n\
+            Frequency A: 16 antinodes\n\
+            Frequencies 0, A share the antinode at (1, 3)\n\
+            Frequencies 0, A share the antinode at (4, 4)\n\
+            Frequencies 0, A share the antinode at (5, 6)"
+        );
+        let by_frequency = Day {
+            antennas: HashMap::from([('a', vec![Point::new(3, 4), Point::new(5, 5)])]),
+            grid: Grid::new(10, 10, '.'),
+        }
+        .antinodes_by_frequency(PART_1_RULE);
+        assert_eq!(
+            by_frequency,
+            BTreeMap::from([('a', BTreeSet::from([Point::new(1, 3), Point::new(7, 6)]))])
+        );
+    }
+
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/08")).unwrap();
@@ -239,6 +304,87 @@ mod tests {
         assert_eq!(puzzle.solve_part_2(), "34");
     }
 
+    /// Finds the antinodes by checking every point in the grid against the rule's definition.
+    fn find_antinodes_brute_force(
+        a: Point,
+        b: Point,
+        rule: AntinodeRule,
+        grid: &Grid<char>,
+    ) -> Vec<Point> {
+        let squared_distance = |p: Point, q: Point| {
+            (p.row - q.row) * (p.row - q.row) + (p.col - q.col) * (p.col - q.col)
+        };
+        grid.points()
+            .filter(|&p| (p.row - a.row) * (b.col - a.col) == (p.col - a.col) * (b.row - a.row))
+            .filter(|&p| match rule {
+                AntinodeRule::Ratio {
+                    ratio,
+                    include_between,
+                } => {
+                    let ratio = ratio.get() as i32;
+                    let (to_a, to_b) = (squared_distance(p, a), squared_distance(p, b));
+                    let between =
+                        (p.row - a.row) * (p.row - b.row) + (p.col - a.col) * (p.col - b.col) < 0;
+                    (to_a == ratio * ratio * to_b || to_b == ratio * ratio * to_a)
+                        && (include_between || !between)
+                }
+                AntinodeRule::Collinear => true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_antinodes_matches_brute_force() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(8);
+        let mut rules = vec![AntinodeRule::Collinear];
+        for ratio in 1..5 {
+            for include_between in [false, true] {
+                rules.push(AntinodeRule::Ratio {
+                    ratio: NonZeroU32::new(ratio).unwrap(),
+                    include_between,
+                });
+            }
+        }
+        for _ in 0..500 {
+            let grid = Grid::new(rng.random_range(1..20), rng.random_range(1..20), '.');
+            let mut random_point = || {
+                Point::new(
+                    rng.random_range(0..grid.height() as i32),
+                    rng.random_range(0..grid.width() as i32),
+                )
+            };
+            let (a, b) = (random_point(), random_point());
+            if a == b {
+                continue;
+            }
+            for &rule in &rules {
+                let actual = find_antinodes(a, b, rule, &grid)
+                    .into_iter()
+                    .sorted()
+                    .dedup();
+                let expected = find_antinodes_brute_force(a, b, rule, &grid);
+                assert_eq!(actual.collect_vec(), expected, "{:?} {:?} {:?}", a, b, rule);
+            }
+        }
+    }
+
+    #[test]
+    fn test_collinear_lattice_points() {
+        // Stepping by the raw offset of (2, 4) would skip (1, 2) and (3, 6).
+        let grid = Grid::new(5, 9, '.');
+        let antinodes = find_antinodes(
+            Point::new(0, 0),
+            Point::new(2, 4),
+            AntinodeRule::Collinear,
+            &grid,
+        );
+        assert_eq!(
+            antinodes.into_iter().sorted().collect_vec(),
+            (0..5).map(|row| Point::new(row, 2 * row)).collect_vec()
+        );
+    }
+
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/08")).unwrap();
@@ -1,5 +1,10 @@
 use crate::puzzle::Puzzle;
+use itertools::Itertools;
 use rangemap::RangeMap;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fmt::Write;
 use std::ops::Range;
 
 pub struct Day {
@@ -26,8 +31,54 @@ impl Puzzle for Day {
         disk.defrag_files();
         disk.checksum().to_string()
     }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        let show: fn(&Disk) -> String = match mode {
+            "disk" => |disk| disk.to_string(),
+            "summary" => Disk::summary,
+            "moves" => return Some(self.moves()),
+            _ => return None,
+        };
+        let disk = Disk::create_from_map(&self.input);
+        let mut blocks = disk.clone();
+        blocks.defrag_blocks();
+        let mut files = disk.clone();
+        files.defrag_files();
+        Some(format!(
+            "Initial:\n{}\n\nBlocks compacted:\n{}\n\nFiles compacted:\n{}",
+            show(&disk),
+            show(&blocks),
+            show(&files)
+        ))
+    }
+}
+
+/// A contiguous stretch of blocks that either all belong to the same file or are all free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Run {
+    id: Option<usize>,
+    len: usize,
+}
+
+/// A whole file being moved into a gap further left on the disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileMove {
+    id: usize,
+    from: Range<usize>,
+    to: Range<usize>,
+}
+
+impl fmt::Display for FileMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Move file {} from {:?} to {:?}",
+            self.id, self.from, self.to
+        )
+    }
 }
 
+#[derive(Clone)]
 struct Disk {
     files: RangeMap<usize, usize>,
     size: Range<usize>,
@@ -78,21 +129,96 @@ impl Disk {
         }
     }
 
+    /// Compacts the disk a whole file at a time, moving each file (from the last to the first) into
+    /// the leftmost gap that fits it, provided that gap is to the file's left.
+    ///
+    /// Gaps never grow, and no file is ever moved into the space another file has left behind, so
+    /// the starts of the gaps are kept in a min-heap per gap length. The leftmost gap that fits a
+    /// file is then the earliest start among the heaps for lengths at least as long as the file,
+    /// and whatever's left of the gap goes back into the heap for its new length.
+    ///
+    /// Time complexity: O(n * (k + log n)) where n is the number of files and k the longest gap
+    /// Auxiliary space complexity: O(n)
     fn defrag_files(&mut self) {
-        for (range, &id) in self.files.clone().iter().rev() {
-            for gap in self.files.gaps(&self.size) {
-                if gap.start > range.start {
-                    break;
-                }
-                if gap.len() >= range.len() {
-                    self.files.remove(range.clone());
-                    self.files.insert(gap.start..gap.start + range.len(), id);
-                    break;
+        let mut gaps: Vec<BinaryHeap<Reverse<usize>>> = Vec::new();
+        for gap in self.files.gaps(&self.size) {
+            if gaps.len() <= gap.len() {
+                gaps.resize_with(gap.len() + 1, BinaryHeap::new);
+            }
+            gaps[gap.len()].push(Reverse(gap.start));
+        }
+        let files = self
+            .files
+            .iter()
+            .map(|(range, &id)| (range.clone(), id))
+            .collect::<Vec<_>>();
+        for (from, id) in files.into_iter().rev() {
+            let leftmost = (from.len()..gaps.len())
+                .filter_map(|len| gaps[len].peek().map(|&Reverse(start)| (start, len)))
+                .min();
+            if let Some((start, len)) = leftmost
+                && start < from.start
+            {
+                gaps[len].pop();
+                if len > from.len() {
+                    gaps[len - from.len()].push(Reverse(start + from.len()));
                 }
+                self.files.remove(from.clone());
+                self.files.insert(start..start + from.len(), id);
             }
         }
     }
 
+    /// Compacts the disk a whole file at a time like `defrag_files`, but by scanning the gaps from
+    /// the start of the disk for every file, yielding each move as it's made. Files that don't fit
+    /// in any gap to their left stay put and aren't yielded.
+    fn file_moves(&mut self) -> FileMoves<'_> {
+        let files = self
+            .files
+            .iter()
+            .map(|(range, &id)| (range.clone(), id))
+            .collect();
+        FileMoves { disk: self, files }
+    }
+
+    /// Returns the runs of blocks from the start of the disk to its end.
+    fn runs(&self) -> Vec<Run> {
+        let mut runs = Vec::new();
+        let mut position = self.size.start;
+        for (range, &id) in self.files.iter() {
+            if range.start > position {
+                runs.push(Run {
+                    id: None,
+                    len: range.start - position,
+                });
+            }
+            runs.push(Run {
+                id: Some(id),
+                len: range.len(),
+            });
+            position = range.end;
+        }
+        if self.size.end > position {
+            runs.push(Run {
+                id: None,
+                len: self.size.end - position,
+            });
+        }
+        runs
+    }
+
+    /// Describes the disk as a list of runs, e.g. `0x2 .x3 1x3` for `00...111`, which stays
+    /// readable for disks far too big to draw block by block.
+    fn summary(&self) -> String {
+        self.runs()
+            .iter()
+            .map(|run| match run.id {
+                Some(id) => format!("{}x{}", id, run.len),
+                None => format!(".x{}", run.len),
+            })
+            .join(" ")
+    }
+
     fn checksum(&self) -> usize {
         self.files
             .iter()
@@ -104,17 +230,77 @@ impl Disk {
     }
 }
 
+/// Draws the disk in the puzzle's notation, with one character per block: the id of the file it
+/// belongs to, or `.` if it's free. Ids above 9 don't fit in one character, so each of their blocks
+/// is drawn as the id in parentheses.
+impl fmt::Display for Disk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for run in self.runs() {
+            let block = match run.id {
+                Some(id) if id < 10 => id.to_string(),
+                Some(id) => format!("({})", id),
+                None => ".".to_string(),
+            };
+            write!(f, "{}", block.repeat(run.len))?;
+        }
+        Ok(())
+    }
+}
+
+/// The iterator returned by `Disk::file_moves`.
+struct FileMoves<'a> {
+    disk: &'a mut Disk,
+    /// The files that haven't been considered yet, the last of which is considered next.
+    files: Vec<(Range<usize>, usize)>,
+}
+
+impl Iterator for FileMoves<'_> {
+    type Item = FileMove;
+
+    fn next(&mut self) -> Option<FileMove> {
+        while let Some((from, id)) = self.files.pop() {
+            let gap = self
+                .disk
+                .files
+                .gaps(&self.disk.size)
+                .take_while(|gap| gap.start < from.start)
+                .find(|gap| gap.len() >= from.len());
+            if let Some(gap) = gap {
+                let to = gap.start..gap.start + from.len();
+                self.disk.files.remove(from.clone());
+                self.disk.files.insert(to.clone(), id);
+                return Some(FileMove { id, from, to });
+            }
+        }
+        None
+    }
+}
+
 impl Day {
     pub fn create(input: &str) -> Box<dyn Puzzle> {
         Box::new(Day {
             input: input.trim().to_string(),
         })
     }
+
+    /// Lists every file move made while compacting the disk a whole file at a time, along with the
+    /// disk after each move.
+    fn moves(&self) -> String {
+        let mut disk = Disk::create_from_map(&self.input);
+        let mut output = format!("Initial:\n{}\n", disk);
+        let mut file_moves = disk.file_moves();
+        while let Some(file_move) = file_moves.next() {
+            write!(output, "\n{}:\n{}\n", file_move, file_moves.disk).unwrap();
+        }
+        output.trim_end().to_string()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
     use std::path::PathBuf;
 
     #[test]
@@ -124,6 +310,89 @@ mod tests {
         assert_eq!(puzzle.solve_part_1(), "1928");
     }
 
+    #[test]
+    fn test_render() {
+        let disk = Disk::create_from_map("12345");
+        assert_eq!(disk.to_string(), "0..111....22222");
+        assert_eq!(disk.summary(), "0x1 .x2 1x3 .x4 2x5");
+        let mut blocks = disk.clone();
+        blocks.defrag_blocks();
+        assert_eq!(blocks.to_string(), "022111222......");
+        assert_eq!(blocks.summary(), "0x1 2x2 1x3 2x3 .x6");
+        let disk = Disk::create_from_map("1010101010101010101010");
+        assert_eq!(disk.to_string(), "0123456789(10)");
+    }
+
+    #[test]
+    fn test_inspect_disk() {
+        let input = "2333133121414131402";
+        let puzzle = Day::create(input);
+        assert_eq!(
+            puzzle.inspect("disk").unwrap(),
+            "\
+            Initial:\n\
+            00...111...2...333.44.5555.6666.777.888899\n\
+            \n\
+            Blocks compacted:\n\
+            0099811188827773336446555566..............\n\
+            \n\
+            Files compacted:\n\
+            00992111777.44.333....5555.6666.....8888.."
+        );
+    }
+
+    #[test]
+    fn test_file_moves() {
+        let mut disk = Disk::create_from_map("2333133121414131402");
+        let moves = disk.file_moves().collect::<Vec<_>>();
+        assert_eq!(
+            moves,
+            vec![
+                FileMove {
+                    id: 9,
+                    from: 40..42,
+                    to: 2..4
+                },
+                FileMove {
+                    id: 7,
+                    from: 32..35,
+                    to: 8..11
+                },
+                FileMove {
+                    id: 4,
+                    from: 19..21,
+                    to: 12..14
+                },
+                FileMove {
+                    id: 2,
+                    from: 11..12,
+                    to: 4..5
+                },
+            ]
+        );
+        assert_eq!(
+            disk.to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+        assert_eq!(disk.checksum(), 2858);
+
+        let puzzle = Day::create("2333133121414131402");
+        let moves = puzzle.inspect("moves").unwrap();
+        assert!(moves.starts_with(
+            "\
+            Initial:\n\
+            00...111...2...333.44.5555.6666.777.888899\n\
+            \n\
+            Move file 9 from 40..42 to 2..4:\n\
+            0099.111...2...333.44.5555.6666.777.8888..\n"
+        ));
+        assert!(moves.ends_with(
+            "\
+            Move file 2 from 11..12 to 4..5:\n\
+            00992111777.44.333....5555.6666.....8888.."
+        ));
+    }
+
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/09")).unwrap();
@@ -138,6 +407,57 @@ mod tests {
         assert_eq!(puzzle.solve_part_2(), "2858");
     }
 
+    /// Generates a random disk map with the given number of files, each 1 to 9 blocks long and
+    /// followed by 0 to 9 free blocks.
+    fn random_map(rng: &mut impl Rng, files: usize) -> String {
+        (0..files)
+            .flat_map(|_| [rng.random_range(1..=9), rng.random_range(0..=9)])
+            .map(|digit| char::from_digit(digit, 10).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_defrag_files_matches_file_moves() {
+        let mut rng = StdRng::seed_from_u64(9);
+        for files in [1, 2, 5, 10, 100, 1000] {
+            for _ in 0..20 {
+                let map = random_map(&mut rng, files);
+                let mut fast = Disk::create_from_map(&map);
+                fast.defrag_files();
+                let mut slow = Disk::create_from_map(&map);
+                slow.file_moves().for_each(drop);
+                assert_eq!(fast.files, slow.files, "{}", map);
+            }
+        }
+    }
+
+    #[test]
+    fn test_defrag_files_stress() {
+        // 20,000 digits
+        let mut rng = StdRng::seed_from_u64(9);
+        let map = random_map(&mut rng, 10_000);
+        let mut fast = Disk::create_from_map(&map);
+        fast.defrag_files();
+        let mut slow = Disk::create_from_map(&map);
+        slow.file_moves().for_each(drop);
+        assert_eq!(fast.checksum(), slow.checksum());
+        assert_eq!(fast.files, slow.files);
+    }
+
+    #[test]
+    #[ignore = "the scanning implementation takes minutes on a disk this big, even in release mode"]
+    fn test_defrag_files_stress_large() {
+        // 300,000 digits
+        let mut rng = StdRng::seed_from_u64(9);
+        let map = random_map(&mut rng, 150_000);
+        let mut fast = Disk::create_from_map(&map);
+        fast.defrag_files();
+        let mut slow = Disk::create_from_map(&map);
+        slow.file_moves().for_each(drop);
+        assert_eq!(fast.checksum(), slow.checksum());
+        assert_eq!(fast.files, slow.files);
+    }
+
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/09")).unwrap();
@@ -1,110 +1,140 @@
-use crate::puzzle::Puzzle;
-use itertools::iproduct;
-use petgraph::algo::{DfsSpace, all_simple_paths, has_path_connecting};
-use petgraph::graph::{Graph, NodeIndex};
-use std::hash::RandomState;
+use crate::grid::{Grid, Point};
+use crate::puzzle::{Puzzle, PuzzleParams};
+use std::collections::HashSet;
 
 pub struct Day {
-    graph: Graph<(usize, usize, usize), ()>,
-    starts: Vec<NodeIndex>,
-    ends: Vec<NodeIndex>,
+    heights: Grid<Option<u32>>,
+    trailhead_height: u32,
+    peak_height: u32,
+}
+
+/// The score of a trailhead (the number of distinct peaks its trails reach) and its rating (the
+/// number of distinct trails leading from it to any peak).
+#[derive(Debug, PartialEq, Eq)]
+struct Trailhead {
+    score: usize,
+    rating: u64,
 }
 
 impl Puzzle for Day {
-    /// TODO
+    /// Sums the scores of all trailheads.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n * p) where n is the number of cells and p the number of peaks
+    /// Auxiliary space complexity: O(n * p)
     fn solve_part_1(&self) -> String {
-        let mut space = DfsSpace::new(&self.graph);
-        iproduct!(self.starts.iter(), self.ends.iter())
-            .filter(|&(&start, &end)| self.possibly_reachable(start, end))
-            .map(|(&start, &end)| {
-                if has_path_connecting(&self.graph, start, end, Some(&mut space)) {
-                    1
-                } else {
-                    0
-                }
-            })
+        self.trailheads()
+            .iter()
+            .map(|trailhead| trailhead.score)
             .sum::<usize>()
             .to_string()
     }
 
-    /// TODO
+    /// Sums the ratings of all trailheads.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n * p) where n is the number of cells and p the number of peaks
+    /// Auxiliary space complexity: O(n * p)
     fn solve_part_2(&self) -> String {
-        iproduct!(self.starts.iter(), self.ends.iter())
-            .filter(|&(&start, &end)| self.possibly_reachable(start, end))
-            .map(|(&start, &end)| {
-                all_simple_paths::<Vec<_>, _, RandomState>(&self.graph, start, end, 8, Some(8))
-                    .count()
-            })
-            .sum::<usize>()
+        self.trailheads()
+            .iter()
+            .map(|trailhead| trailhead.rating)
+            .sum::<u64>()
             .to_string()
     }
 }
 
 impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
-        let mut graph = Graph::new();
-        let grid = input
-            .lines()
-            .map(|line| {
-                line.chars()
-                    .map(|c| c.to_digit(10).unwrap() as usize)
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        let nodes = grid
-            .iter()
-            .enumerate()
-            .map(|(row, line)| {
-                line.iter()
-                    .enumerate()
-                    .map(|(col, &value)| graph.add_node((row, col, value)))
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
-        let starts = iproduct!(0..nodes.len(), 0..nodes[0].len())
-            .filter(|&(row, col)| grid[row][col] == 0)
-            .map(|(row, col)| nodes[row][col])
-            .collect::<Vec<_>>();
-        let ends = iproduct!(0..nodes.len(), 0..nodes[0].len())
-            .filter(|&(row, col)| grid[row][col] == 9)
-            .map(|(row, col)| nodes[row][col])
-            .collect::<Vec<_>>();
-        for (row, col) in iproduct!(0..nodes.len(), 0..nodes[0].len()) {
-            if row > 0 && grid[row][col] + 1 == grid[row - 1][col] {
-                graph.add_edge(nodes[row][col], nodes[row - 1][col], ());
-            }
-            if col > 0 && grid[row][col] + 1 == grid[row][col - 1] {
-                graph.add_edge(nodes[row][col], nodes[row][col - 1], ());
-            }
-            if row + 1 < nodes.len() && grid[row][col] + 1 == grid[row + 1][col] {
-                graph.add_edge(nodes[row][col], nodes[row + 1][col], ());
-            }
-            if col + 1 < nodes[0].len() && grid[row][col] + 1 == grid[row][col + 1] {
-                graph.add_edge(nodes[row][col], nodes[row][col + 1], ());
-            }
-        }
+    pub fn create(input: &str, params: &PuzzleParams) -> Box<dyn Puzzle> {
+        let radix = params.get("radix", 10);
+        assert!(
+            (2..=36).contains(&radix),
+            "Heights must be written in a radix between 2 and 36"
+        );
+        let trailhead_height = params.get("trailhead", 0);
+        let peak_height = params.get("peak", 9);
+        assert!(
+            trailhead_height <= peak_height,
+            "Trailheads can't be higher than peaks"
+        );
         Box::new(Day {
-            graph,
-            starts,
-            ends,
+            heights: parse_heights(input, radix),
+            trailhead_height,
+            peak_height,
         })
     }
 
-    fn possibly_reachable(&self, start: NodeIndex, end: NodeIndex) -> bool {
-        let (start_row, start_col, _) = self.graph[start];
-        let (end_row, end_col, _) = self.graph[end];
-        let distance = (start_row as isize - end_row as isize).abs()
-            + (start_col as isize - end_col as isize).abs();
-        distance <= 9
+    /// Scores and rates every trailhead, in row-major order, in a single sweep down the heights,
+    /// from the peaks to the trailheads. Every cell learns which peaks it can reach and along how
+    /// many trails by combining the results of its neighbors one level up, so no trail is ever
+    /// walked twice.
+    fn trailheads(&self) -> Vec<Trailhead> {
+        let mut peaks: Grid<HashSet<Point>> = self.heights.map(|_| HashSet::new());
+        let mut ratings = self.heights.map(|_| 0u64);
+        for point in self.heights.positions(|&h| h == Some(self.peak_height)) {
+            peaks[point].insert(point);
+            ratings[point] = 1;
+        }
+        for height in (self.trailhead_height..self.peak_height).rev() {
+            for point in self.heights.positions(|&h| h == Some(height)) {
+                for neighbor in self.heights.neighbors4(point) {
+                    if self.heights[neighbor] == Some(height + 1) {
+                        ratings[point] += ratings[neighbor];
+                        let reachable = peaks[neighbor].clone();
+                        peaks[point].extend(reachable);
+                    }
+                }
+            }
+        }
+        self.heights
+            .positions(|&h| h == Some(self.trailhead_height))
+            .map(|position| Trailhead {
+                score: peaks[position].len(),
+                rating: ratings[position],
+            })
+            .collect()
     }
 }
 
+/// Parses the topographic map, where `.` marks an impassable cell. Each height is normally a single
+/// digit in the given radix, but rows that separate their heights with whitespace or commas can
+/// use several digits per height.
+fn parse_heights(input: &str, radix: u32) -> Grid<Option<u32>> {
+    let parse_height = |token: &str| match token {
+        "." => None,
+        _ => Some(
+            u32::from_str_radix(token, radix)
+                .unwrap_or_else(|_| panic!("Invalid height: {}", token)),
+        ),
+    };
+    let is_separator = |c: char| c.is_whitespace() || c == ',';
+    let rows = input
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            if line.contains(is_separator) {
+                line.split(is_separator)
+                    .filter(|token| !token.is_empty())
+                    .map(parse_height)
+                    .collect::<Vec<_>>()
+            } else {
+                line.char_indices()
+                    .map(|(i, c)| parse_height(&line[i..i + c.len_utf8()]))
+                    .collect()
+            }
+        })
+        .collect::<Vec<_>>();
+    let width = rows.first().map_or(0, |row| row.len());
+    assert!(
+        rows.iter().all(|row| row.len() == width),
+        "Grid rows must be the same width"
+    );
+    let mut heights = Grid::new(rows.len(), width, None);
+    for (point, height) in heights.points().zip(rows.into_iter().flatten()) {
+        heights[point] = height;
+    }
+    heights
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -121,14 +151,56 @@ mod tests {
             32019012\n\
             01329801\n\
             10456732";
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "36");
     }
 
+    #[test]
+    fn test_part_1_example_2() {
+        let input = "\
+            ...0...\n\
+            ...1...\n\
+            ...2...\n\
+            6543456\n\
+            7.....7\n\
+            8.....8\n\
+            9.....9";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(puzzle.solve_part_1(), "2");
+    }
+
+    #[test]
+    fn test_part_1_example_3() {
+        let input = "\
+            ..90..9\n\
+            ...1.98\n\
+            ...2..7\n\
+            6543456\n\
+            765.987\n\
+            876....\n\
+            987....";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(puzzle.solve_part_1(), "4");
+    }
+
+    #[test]
+    fn test_part_1_example_4() {
+        let input = "\
+            10..9..\n\
+            2...8..\n\
+            3...7..\n\
+            4567654\n\
+            ...8..3\n\
+            ...9..2\n\
+            .....01";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(puzzle.solve_part_1(), "3");
+    }
+
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/10")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "468");
     }
 
@@ -143,14 +215,165 @@ mod tests {
             32019012\n\
             01329801\n\
             10456732";
-        let puzzle = Day::create(input);
+        let puzzle = Day::create(input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "81");
     }
 
+    #[test]
+    fn test_part_2_example_2() {
+        let input = "\
+            .....0.\n\
+            ..4321.\n\
+            ..5..2.\n\
+            ..6543.\n\
+            ..7..4.\n\
+            ..8765.\n\
+            ..9....";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(puzzle.solve_part_2(), "3");
+    }
+
+    #[test]
+    fn test_part_2_example_3() {
+        let input = "\
+            012345\n\
+            123456\n\
+            234567\n\
+            345678\n\
+            4.6789\n\
+            56789.";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(puzzle.solve_part_2(), "227");
+    }
+
+    /// The original implementation, which searches the graph of uphill steps from every trailhead
+    /// to every peak.
+    fn trailheads_with_graph(day: &Day) -> Vec<Trailhead> {
+        use petgraph::algo::{all_simple_paths, has_path_connecting};
+        use petgraph::graph::Graph;
+        use std::hash::RandomState;
+
+        let heights = &day.heights;
+        let intermediate = (day.peak_height - day.trailhead_height - 1) as usize;
+        let mut graph = Graph::<(), ()>::new();
+        let nodes = heights.map(|_| graph.add_node(()));
+        for point in heights.points() {
+            for neighbor in heights.neighbors4(point) {
+                if let (Some(from), Some(to)) = (heights[point], heights[neighbor])
+                    && from + 1 == to
+                {
+                    graph.add_edge(nodes[point], nodes[neighbor], ());
+                }
+            }
+        }
+        let peaks = heights
+            .positions(|&h| h == Some(day.peak_height))
+            .collect::<Vec<_>>();
+        heights
+            .positions(|&h| h == Some(day.trailhead_height))
+            .map(|start| Trailhead {
+                score: peaks
+                    .iter()
+                    .filter(|&&end| has_path_connecting(&graph, nodes[start], nodes[end], None))
+                    .count(),
+                rating: peaks
+                    .iter()
+                    .map(|&end| {
+                        all_simple_paths::<Vec<_>, _, RandomState>(
+                            &graph,
+                            nodes[start],
+                            nodes[end],
+                            intermediate,
+                            Some(intermediate),
+                        )
+                        .count() as u64
+                    })
+                    .sum(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trailheads_match_graph_search() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(10);
+        for _ in 0..100 {
+            // Heights mostly increase towards the bottom right so that there are plenty of trails.
+            let size = rng.random_range(4..12);
+            let radix = rng.random_range(2..=16);
+            let input = (0..size)
+                .map(|row| {
+                    (0..size)
+                        .map(|col| {
+                            if rng.random_bool(0.1) {
+                                '.'
+                            } else {
+                                let height = (row + col + rng.random_range(0..3)) % radix;
+                                char::from_digit(height, radix).unwrap()
+                            }
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let trailhead_height = rng.random_range(0..radix - 1);
+            let day = Day {
+                heights: parse_heights(&input, radix),
+                trailhead_height,
+                peak_height: rng.random_range(trailhead_height + 1..radix),
+            };
+            assert_eq!(day.trailheads(), trailheads_with_graph(&day), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_hex_heights() {
+        let input = "\
+            0123\n\
+            bcd4\n\
+            afe5\n\
+            9876";
+        let params = PuzzleParams::default().with("radix", 16).with("peak", 15);
+        let puzzle = Day::create(input, &params);
+        assert_eq!(puzzle.solve_part_1(), "1");
+        assert_eq!(puzzle.solve_part_2(), "1");
+        let params = PuzzleParams::default()
+            .with("radix", 16)
+            .with("trailhead", 3);
+        let puzzle = Day::create(input, &params);
+        assert_eq!(puzzle.solve_part_1(), "1");
+        assert_eq!(puzzle.solve_part_2(), "1");
+    }
+
+    #[test]
+    fn test_multi_digit_heights() {
+        let input = "\
+            10 11 12 13\n\
+            11 12, .  14\n\
+            12 13 14 15";
+        let params = PuzzleParams::default()
+            .with("trailhead", 10)
+            .with("peak", 15);
+        let puzzle = Day::create(input, &params);
+        assert_eq!(puzzle.solve_part_1(), "1");
+        assert_eq!(puzzle.solve_part_2(), "4");
+        assert_eq!(
+            parse_heights(input, 10)[Point::new(1, 2)],
+            None,
+            "Impassable cells can be mixed with separated heights"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid height: g")]
+    fn test_invalid_height() {
+        parse_heights("01\ng2", 16);
+    }
+
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/10")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "966");
     }
 }
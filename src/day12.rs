@@ -1,112 +1,204 @@
+use crate::grid::{Direction, Grid, Point};
 use crate::puzzle::Puzzle;
-use geo::line_measures::LengthMeasurable;
-use geo::{Area, BooleanOps, CoordsIter, Euclidean, MultiPolygon, polygon};
-use itertools::iproduct;
-use petgraph::unionfind::UnionFind;
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::fmt::Write;
 
 pub struct Day {
-    polygons: Vec<MultiPolygon>,
+    regions: Vec<Region>,
+}
+
+/// A maximal group of orthogonally connected plots growing the same plant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Region {
+    plant: char,
+    area: usize,
+    perimeter: usize,
+    sides: usize,
+    /// The number of pockets of other plants that the region completely surrounds.
+    holes: usize,
+    /// The smallest box containing the region, as its top-left and bottom-right plots.
+    top_left: Point,
+    bottom_right: Point,
 }
 
 impl Puzzle for Day {
-    /// TODO
+    /// Sums the area times the perimeter of every region.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n) where n is the number of plots
+    /// Auxiliary space complexity: O(n)
     fn solve_part_1(&self) -> String {
-        self.polygons
+        self.regions
             .iter()
-            .map(|multi_polygon| {
-                let area = multi_polygon.unsigned_area() as usize;
-                let perimeter = multi_polygon
-                    .iter()
-                    .map(|polygon| {
-                        polygon.exterior().length(&Euclidean) as usize
-                            + polygon
-                                .interiors()
-                                .iter()
-                                .map(|interior| interior.length(&Euclidean) as usize)
-                                .sum::<usize>()
-                    })
-                    .sum::<usize>();
-                area * perimeter
-            })
+            .map(Region::price)
             .sum::<usize>()
             .to_string()
     }
 
-    /// TODO
+    /// Sums the area times the number of sides of every region.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n) where n is the number of plots
+    /// Auxiliary space complexity: O(n)
     fn solve_part_2(&self) -> String {
-        self.polygons
+        self.regions
             .iter()
-            .map(|multi_polygon| {
-                let area = multi_polygon.unsigned_area() as usize;
-                let exterior_sides = multi_polygon.exterior_coords_iter().count() - 1;
-                let interior_sides = multi_polygon
-                    .iter()
-                    .map(|polygon| {
-                        polygon
-                            .interiors()
-                            .iter()
-                            .map(|interior| interior.exterior_coords_iter().count() - 1)
-                            .sum::<usize>()
-                    })
-                    .sum::<usize>();
-                area * (exterior_sides + interior_sides)
-            })
+            .map(Region::bulk_price)
             .sum::<usize>()
             .to_string()
     }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        match mode {
+            "regions" => Some(self.report()),
+            _ => None,
+        }
+    }
+}
+
+impl Region {
+    /// The price of fencing the region, priced by its perimeter.
+    fn price(&self) -> usize {
+        self.area * self.perimeter
+    }
+
+    /// The price of fencing the region with the bulk discount, priced by its number of sides.
+    fn bulk_price(&self) -> usize {
+        self.area * self.sides
+    }
 }
 
 impl Day {
     pub fn create(input: &str) -> Box<dyn Puzzle> {
-        let grid = input
-            .lines()
-            .map(|line| line.chars().collect::<Vec<char>>())
-            .collect::<Vec<Vec<char>>>();
-        let height = grid.len();
-        let width = grid[0].len();
-
-        let mut union_find: UnionFind<usize> = UnionFind::new(height * width);
-        for (row, col) in iproduct!(0..height, 0..width) {
-            if row > 0 && grid[row][col] == grid[row - 1][col] {
-                union_find.union((row - 1) * width + col, row * width + col);
-            }
-            if col > 0 && grid[row][col] == grid[row][col - 1] {
-                union_find.union(row * width + col - 1, row * width + col);
-            }
+        Box::new(Day {
+            regions: find_regions(&Grid::parse(input)),
+        })
+    }
+
+    /// Returns the regions from the most to the least expensive to fence, by their undiscounted
+    /// price and then by their bulk price. Equally priced regions stay in row-major order.
+    fn regions_by_cost(&self) -> Vec<&Region> {
+        let mut regions = self.regions.iter().collect::<Vec<_>>();
+        regions.sort_by_key(|region| Reverse((region.price(), region.bulk_price())));
+        regions
+    }
+
+    /// Lists every region with its measurements and both of its prices, most expensive first,
+    /// followed by the totals.
+    fn report(&self) -> String {
+        let mut output = format!(
+            "{:<5} {:>6} {:>9} {:>6} {:>6} {:>8} {:>10}\n",
+            "Plant", "Area", "Perimeter", "Sides", "Holes", "Price", "Bulk price"
+        );
+        for region in self.regions_by_cost() {
+            writeln!(
+                output,
+                "{:<5} {:>6} {:>9} {:>6} {:>6} {:>8} {:>10}",
+                region.plant,
+                region.area,
+                region.perimeter,
+                region.sides,
+                region.holes,
+                region.price(),
+                region.bulk_price()
+            )
+            .unwrap();
         }
+        write!(
+            output,
+            "{:<36} {:>8} {:>10}",
+            "Total",
+            self.solve_part_1(),
+            self.solve_part_2()
+        )
+        .unwrap();
+        output
+    }
+}
 
-        let mut polygons: HashMap<usize, MultiPolygon> = HashMap::new();
-        for (row, col) in iproduct!(0..height, 0..width) {
-            let region = polygon![
-                (x: col as f64, y: row as f64),
-                (x: col as f64 + 1.0, y: row as f64),
-                (x: col as f64 + 1.0, y: row as f64 + 1.0),
-                (x: col as f64, y: row as f64 + 1.0),
-            ];
-
-            let index = union_find.find(row * width + col);
-            if let Some(root) = polygons.get_mut(&index) {
-                *root = root.union(&region);
-            } else {
-                polygons.insert(index, MultiPolygon::new(vec![region]));
+/// Flood fills the garden into its regions, in row-major order of their first plot. A region has
+/// as many sides as corners, so sides are counted by looking at the corners of every plot: a
+/// corner is convex when neither orthogonal neighbor around it is in the region, and concave when
+/// both are but the diagonal neighbor between them isn't. The pockets of other plants a region
+/// surrounds are counted from its corners, without flood filling them.
+fn find_regions(garden: &Grid<char>) -> Vec<Region> {
+    let same_plant = |point: Point, other: Point| garden.get(other) == Some(&garden[point]);
+    let mut labels = garden.map(|_| None);
+    let mut regions = Vec::new();
+    for start in garden.points() {
+        if labels[start].is_some() {
+            continue;
+        }
+        let label = regions.len();
+        labels[start] = Some(label);
+        let mut region = Region {
+            plant: garden[start],
+            area: 0,
+            perimeter: 0,
+            sides: 0,
+            holes: 0,
+            top_left: start,
+            bottom_right: start,
+        };
+        let mut stack = vec![start];
+        while let Some(point) = stack.pop() {
+            region.area += 1;
+            region.top_left = Point::new(
+                region.top_left.row.min(point.row),
+                region.top_left.col.min(point.col),
+            );
+            region.bottom_right = Point::new(
+                region.bottom_right.row.max(point.row),
+                region.bottom_right.col.max(point.col),
+            );
+            for dir in Direction::CARDINAL {
+                let neighbor = point.step(dir);
+                if !same_plant(point, neighbor) {
+                    region.perimeter += 1;
+                } else if labels[neighbor].is_none() {
+                    labels[neighbor] = Some(label);
+                    stack.push(neighbor);
+                }
+                let side = point.step(dir.clockwise());
+                let diagonal = neighbor.step(dir.clockwise());
+                match (same_plant(point, neighbor), same_plant(point, side)) {
+                    (false, false) => region.sides += 1,
+                    (true, true) if !same_plant(point, diagonal) => region.sides += 1,
+                    _ => {}
+                }
+            }
+        }
+        regions.push(region);
+    }
+    // Seen as closed unit squares, a region's plots meet along 2 * area + perimeter / 2 distinct
+    // edges, so Euler's formula V - E + F = 1 - holes gives its holes from its distinct corners.
+    let mut corners = vec![0; regions.len()];
+    for row in 0..=garden.height() as i32 {
+        for col in 0..=garden.width() as i32 {
+            let around = [
+                (row - 1, col - 1),
+                (row - 1, col),
+                (row, col - 1),
+                (row, col),
+            ]
+            .map(|(row, col)| labels.get(Point::new(row, col)).copied().flatten());
+            for (i, &label) in around.iter().enumerate() {
+                if let Some(label) = label
+                    && !around[..i].contains(&Some(label))
+                {
+                    corners[label] += 1;
+                }
             }
         }
-        Box::new(Day {
-            polygons: polygons.values().cloned().collect(),
-        })
     }
+    for (region, corners) in regions.iter_mut().zip(corners) {
+        region.holes = 1 + region.area + region.perimeter / 2 - corners;
+    }
+    regions
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
     use std::path::PathBuf;
 
     #[test]
@@ -149,6 +241,280 @@ mod tests {
         assert_eq!(puzzle.solve_part_1(), "1930");
     }
 
+    #[test]
+    fn test_find_regions() {
+        let garden = Grid::parse(
+            "\
+            AAAA\n\
+            BBCD\n\
+            BBCC\n\
+            EEEC",
+        );
+        let region = |plant, area, perimeter, sides, top_left, bottom_right| Region {
+            plant,
+            area,
+            perimeter,
+            sides,
+            holes: 0,
+            top_left,
+            bottom_right,
+        };
+        assert_eq!(
+            find_regions(&garden),
+            vec![
+                region('A', 4, 10, 4, Point::new(0, 0), Point::new(0, 3)),
+                region('B', 4, 8, 4, Point::new(1, 0), Point::new(2, 1)),
+                region('C', 4, 10, 8, Point::new(1, 2), Point::new(3, 3)),
+                region('D', 1, 4, 4, Point::new(1, 3), Point::new(1, 3)),
+                region('E', 3, 8, 4, Point::new(3, 0), Point::new(3, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_report_example_2() {
+        let input = "\
+            OOOOO\n\
+            OXOXO\n\
+            OOOOO\n\
+            OXOXO\n\
+            OOOOO";
+        let puzzle = Day::create(input);
+        assert_eq!(
+            puzzle.inspect("regions").unwrap(),
+            "\
+Plant   Area Perimeter  Sides  Holes    Price Bulk price
+O         21        36     20      4      756        420
+X          1         4      4      0        4          4
+X          1         4      4      0        4          4
+X          1         4      4      0        4          4
+X          1         4      4      0        4          4
+Total                                     772        436"
+        );
+    }
+
+    #[test]
+    fn test_report_example_3() {
+        let input = "\
+            EEEEE\n\
+            EXXXX\n\
+            EEEEE\n\
+            EXXXX\n\
+            EEEEE";
+        let puzzle = Day::create(input);
+        assert_eq!(
+            puzzle.inspect("regions").unwrap(),
+            "\
+Plant   Area Perimeter  Sides  Holes    Price Bulk price
+E         17        36     12      0      612        204
+X          4        10      4      0       40         16
+X          4        10      4      0       40         16
+Total                                     692        236"
+        );
+    }
+
+    #[test]
+    fn test_bounding_boxes() {
+        let garden = Grid::parse(
+            "\
+            RRRRIICCFF\n\
+            RRRRIICCCF\n\
+            VVRRRCCFFF\n\
+            VVRCCCJFFF\n\
+            VVVVCJJCFE\n\
+            VVIVCCJJEE\n\
+            VVIIICJJEE\n\
+            MIIIIIJJEE\n\
+            MIIISIJEEE\n\
+            MMMISSJEEE",
+        );
+        let boxes = find_regions(&garden)
+            .iter()
+            .map(|region| {
+                let (top_left, bottom_right) = (region.top_left, region.bottom_right);
+                (
+                    region.plant,
+                    (top_left.row, top_left.col),
+                    (bottom_right.row, bottom_right.col),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            boxes,
+            vec![
+                ('R', (0, 0), (3, 4)),
+                ('I', (0, 4), (1, 5)),
+                ('C', (0, 3), (6, 8)),
+                ('F', (0, 7), (4, 9)),
+                ('V', (2, 0), (6, 3)),
+                ('J', (3, 5), (9, 7)),
+                ('C', (4, 7), (4, 7)),
+                ('E', (4, 7), (9, 9)),
+                ('I', (5, 1), (9, 5)),
+                ('M', (7, 0), (9, 2)),
+                ('S', (8, 4), (9, 5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_count_holes() {
+        let garden = Grid::parse(
+            "\
+            AAAAAA\n\
+            AAABBA\n\
+            AAABBA\n\
+            ABBAAA\n\
+            ABBAAA\n\
+            AAAAAA",
+        );
+        let holes = find_regions(&garden)
+            .iter()
+            .map(|region| (region.plant, region.holes))
+            .collect::<Vec<_>>();
+        assert_eq!(holes, vec![('A', 2), ('B', 0), ('B', 0)]);
+        // A pocket that only touches the outside diagonally is still surrounded.
+        let garden = Grid::parse(
+            "\
+            AAA\n\
+            A.A\n\
+            AA.",
+        );
+        assert_eq!(find_regions(&garden)[0].holes, 1);
+    }
+
+    /// Counts the holes of every region by flood filling the other plots in its bounding box
+    /// instead, counting the groups of them that can't reach the edge of the box.
+    fn count_holes_by_flood_fill(garden: &Grid<char>) -> Vec<usize> {
+        let mut labels = garden.map(|_| usize::MAX);
+        let mut boxes = Vec::new();
+        for start in garden.points() {
+            if labels[start] != usize::MAX {
+                continue;
+            }
+            let label = boxes.len();
+            let (mut top_left, mut bottom_right) = (start, start);
+            let mut stack = vec![start];
+            labels[start] = label;
+            while let Some(point) = stack.pop() {
+                top_left = Point::new(top_left.row.min(point.row), top_left.col.min(point.col));
+                bottom_right = Point::new(
+                    bottom_right.row.max(point.row),
+                    bottom_right.col.max(point.col),
+                );
+                for neighbor in garden.neighbors4(point) {
+                    if labels[neighbor] == usize::MAX && garden[neighbor] == garden[start] {
+                        labels[neighbor] = label;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            boxes.push((top_left, bottom_right));
+        }
+        boxes
+            .iter()
+            .enumerate()
+            .map(|(label, &(top_left, bottom_right))| {
+                let in_box = |point: Point| {
+                    (top_left.row..=bottom_right.row).contains(&point.row)
+                        && (top_left.col..=bottom_right.col).contains(&point.col)
+                };
+                let mut seen = HashSet::new();
+                let mut holes = 0;
+                for start in garden.points().filter(|&point| in_box(point)) {
+                    if labels[start] == label || !seen.insert(start) {
+                        continue;
+                    }
+                    let mut enclosed = true;
+                    let mut stack = vec![start];
+                    while let Some(point) = stack.pop() {
+                        for dir in Direction::CARDINAL {
+                            let neighbor = point.step(dir);
+                            if !in_box(neighbor) {
+                                enclosed = false;
+                            } else if labels[neighbor] != label && seen.insert(neighbor) {
+                                stack.push(neighbor);
+                            }
+                        }
+                    }
+                    if enclosed {
+                        holes += 1;
+                    }
+                }
+                holes
+            })
+            .collect()
+    }
+
+    /// Counts sides by walking the fences instead: a fence on one side of a plot starts a new side
+    /// unless the plot before it (counter-clockwise of the fence's direction) has the same fence.
+    fn count_sides_by_fences(garden: &Grid<char>) -> Vec<usize> {
+        let mut labels = garden.map(|_| usize::MAX);
+        let mut sides = Vec::new();
+        for start in garden.points() {
+            if labels[start] != usize::MAX {
+                continue;
+            }
+            let label = sides.len();
+            let mut stack = vec![start];
+            let mut plots = Vec::new();
+            labels[start] = label;
+            while let Some(point) = stack.pop() {
+                plots.push(point);
+                for neighbor in garden.neighbors4(point) {
+                    if labels[neighbor] == usize::MAX && garden[neighbor] == garden[start] {
+                        labels[neighbor] = label;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            let in_region = |point: Point| garden.get(point).is_some() && labels[point] == label;
+            let has_fence =
+                |point: Point, dir: Direction| in_region(point) && !in_region(point.step(dir));
+            sides.push(
+                plots
+                    .iter()
+                    .flat_map(|&point| Direction::CARDINAL.map(|dir| (point, dir)))
+                    .filter(|&(point, dir)| {
+                        has_fence(point, dir)
+                            && !has_fence(point.step(dir.counter_clockwise()), dir)
+                    })
+                    .count(),
+            );
+        }
+        sides
+    }
+
+    #[test]
+    fn test_sides_and_holes_match_oracles() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(12);
+        for _ in 0..200 {
+            let (height, width) = (rng.random_range(1..10), rng.random_range(1..10));
+            let plants = rng.random_range(1..4);
+            let input = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| (b'A' + rng.random_range(0..plants)) as char)
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            let garden = Grid::parse(&input);
+            let regions = find_regions(&garden);
+            let sides = regions
+                .iter()
+                .map(|region| region.sides)
+                .collect::<Vec<_>>();
+            assert_eq!(sides, count_sides_by_fences(&garden), "{}", input);
+            let holes = regions
+                .iter()
+                .map(|region| region.holes)
+                .collect::<Vec<_>>();
+            assert_eq!(holes, count_holes_by_flood_fill(&garden), "{}", input);
+        }
+    }
+
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/12")).unwrap();
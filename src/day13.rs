@@ -1,50 +1,32 @@
+use crate::number_theory::extended_gcd;
 use crate::puzzle::Puzzle;
 use lazy_regex::regex;
-use rayon::prelude::*;
-use std::ops::{Add, Mul};
-use z3::ast::{Ast, Int};
-use z3::{Context, Optimize};
 
 pub struct Day {
     machines: Vec<ClawMachine>,
 }
 
 impl Puzzle for Day {
-    /// TODO
+    /// We're given a set of claw machines, each with two buttons that move the claw by a fixed
+    /// vector, and asked for the fewest tokens needed to win every winnable prize when each button
+    /// may be pressed at most 100 times.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n log m)
+    /// Auxiliary space complexity: O(1)
     fn solve_part_1(&self) -> String {
         self.machines
-            .par_iter()
-            .filter_map(|machine| {
-                (0..100)
-                    .filter_map(|a| {
-                        let location = (machine.a.0 * a, machine.a.1 * a);
-                        if location.0 > machine.prize.0 || location.1 > machine.prize.1 {
-                            return None;
-                        }
-                        let b = ((machine.prize.0 - location.0) / machine.b.0).max(0);
-                        if location.0 + machine.b.0 * b == machine.prize.0
-                            && location.1 + machine.b.1 * b == machine.prize.1
-                        {
-                            Some(3 * a + b)
-                        } else {
-                            None
-                        }
-                    })
-                    .min()
-            })
-            .sum::<usize>()
+            .iter()
+            .filter_map(|machine| machine.min_cost(Some(100)))
+            .sum::<i128>()
             .to_string()
     }
 
-    /// TODO
+    /// We're given the same claw machines with their prizes moved 10000000000000 units further
+    /// along both axes, and asked for the fewest tokens needed to win every winnable prize.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n log m)
+    /// Auxiliary space complexity: O(1)
     fn solve_part_2(&self) -> String {
-        let ctx = &Context::new(&z3::Config::default());
         self.machines
             .iter()
             .map(|machine| ClawMachine {
@@ -55,33 +37,8 @@ impl Puzzle for Day {
                     machine.prize.1 + 10000000000000,
                 ),
             })
-            .filter_map(|machine| {
-                let opt = Optimize::new(ctx);
-                let a = Int::new_const(ctx, "a");
-                let b = Int::new_const(ctx, "b");
-                let a_x = Int::from_u64(ctx, machine.a.0 as u64);
-                let a_y = Int::from_u64(ctx, machine.a.1 as u64);
-                let b_x = Int::from_u64(ctx, machine.b.0 as u64);
-                let b_y = Int::from_u64(ctx, machine.b.1 as u64);
-                let p_x = Int::from_u64(ctx, machine.prize.0 as u64);
-                let p_y = Int::from_u64(ctx, machine.prize.1 as u64);
-                let cost = a.clone().mul(&Int::from_u64(ctx, 3)).add(&b.clone());
-                let assumptions = [
-                    a.clone().ge(&Int::from_u64(ctx, 0)),
-                    b.clone().ge(&Int::from_u64(ctx, 0)),
-                    a.clone().mul(&a_x).add(&b.clone().mul(&b_x))._eq(&p_x),
-                    a.clone().mul(&a_y).add(&b.clone().mul(&b_y))._eq(&p_y),
-                ];
-                opt.minimize(&cost);
-                if opt.check(&assumptions) == z3::SatResult::Sat {
-                    let model = opt.get_model().unwrap();
-                    let cost = model.eval(&cost, false).unwrap().as_u64().unwrap() as usize;
-                    Some(cost)
-                } else {
-                    None
-                }
-            })
-            .sum::<usize>()
+            .filter_map(|machine| machine.min_cost(None))
+            .sum::<i128>()
             .to_string()
     }
 }
@@ -93,6 +50,92 @@ struct ClawMachine {
     prize: (usize, usize),
 }
 
+impl ClawMachine {
+    /// Returns the fewest tokens needed to move the claw onto the prize, where pressing A costs 3
+    /// tokens and pressing B costs 1 token, or None if the prize can't be reached. If given, each
+    /// button may be pressed at most `max_presses` times.
+    ///
+    /// When the button vectors aren't parallel there is exactly one solution, which we find with
+    /// Cramer's rule. Otherwise the problem reduces to a single linear Diophantine equation along
+    /// the shared direction, whose solutions we enumerate with the extended Euclidean algorithm.
+    fn min_cost(&self, max_presses: Option<i128>) -> Option<i128> {
+        let (a_x, a_y) = (self.a.0 as i128, self.a.1 as i128);
+        let (b_x, b_y) = (self.b.0 as i128, self.b.1 as i128);
+        let (p_x, p_y) = (self.prize.0 as i128, self.prize.1 as i128);
+        let max_presses = max_presses.unwrap_or(i128::MAX);
+        let det = cross(a_x, a_y, b_x, b_y)?;
+        if det != 0 {
+            let a = cross(p_x, p_y, b_x, b_y)?;
+            let b = cross(a_x, a_y, p_x, p_y)?;
+            if a % det != 0 || b % det != 0 {
+                return None;
+            }
+            let (a, b) = (a / det, b / det);
+            if a < 0 || b < 0 || a > max_presses || b > max_presses {
+                return None;
+            }
+            return a.checked_mul(3)?.checked_add(b);
+        }
+
+        // The buttons are parallel, so the prize must lie on the same line through the origin.
+        if cross(a_x, a_y, p_x, p_y)? != 0 || cross(b_x, b_y, p_x, p_y)? != 0 {
+            return None;
+        }
+        if a_x != 0 || b_x != 0 {
+            min_cost_on_line(a_x, b_x, p_x, max_presses)
+        } else {
+            min_cost_on_line(a_y, b_y, p_y, max_presses)
+        }
+    }
+}
+
+/// Returns the z-component of the cross product of (x1, y1) and (x2, y2).
+fn cross(x1: i128, y1: i128, x2: i128, y2: i128) -> Option<i128> {
+    x1.checked_mul(y2)?.checked_sub(y1.checked_mul(x2)?)
+}
+
+/// Minimizes 3a + b subject to u*a + v*b = t and 0 <= a, b <= max_presses, where u and v are
+/// non-negative.
+fn min_cost_on_line(u: i128, v: i128, t: i128, max_presses: i128) -> Option<i128> {
+    if u == 0 && v == 0 {
+        return if t == 0 { Some(0) } else { None };
+    }
+    let (g, x, y) = extended_gcd(u, v);
+    if t % g != 0 {
+        return None;
+    }
+    // Every solution has the form a = a0 + k*step_a, b = b0 - k*step_b.
+    let a0 = x.checked_mul(t / g)?;
+    let b0 = y.checked_mul(t / g)?;
+    let (step_a, step_b) = (v / g, u / g);
+    let mut low = i128::MIN;
+    let mut high = i128::MAX;
+    if step_a == 0 {
+        if a0 < 0 || a0 > max_presses {
+            return None;
+        }
+    } else {
+        low = low.max(-a0.div_euclid(step_a));
+        high = high.min(max_presses.saturating_sub(a0).div_euclid(step_a));
+    }
+    if step_b == 0 {
+        if b0 < 0 || b0 > max_presses {
+            return None;
+        }
+    } else {
+        high = high.min(b0.div_euclid(step_b));
+        low = low.max(-max_presses.saturating_sub(b0).div_euclid(step_b));
+    }
+    if low > high {
+        return None;
+    }
+    // The cost changes by 3*step_a - step_b per step of k, so the optimum is at an endpoint.
+    let k = if 3 * step_a - step_b > 0 { low } else { high };
+    let a = a0.checked_add(k.checked_mul(step_a)?)?;
+    let b = b0.checked_sub(k.checked_mul(step_b)?)?;
+    a.checked_mul(3)?.checked_add(b)
+}
+
 impl Day {
     pub fn create(input: &str) -> Box<dyn Puzzle> {
         let machines = input
@@ -143,6 +186,139 @@ mod tests {
         assert_eq!(puzzle.solve_part_1(), "480");
     }
 
+    #[test]
+    fn test_part_2_example_1() {
+        let input = "\
+            Button A: X+94, Y+34\n\
+            Button B: X+22, Y+67\n\
+            Prize: X=8400, Y=5400\n\
+            \n\
+            Button A: X+26, Y+66\n\
+            Button B: X+67, Y+21\n\
+            Prize: X=12748, Y=12176\n\
+            \n\
+            Button A: X+17, Y+86\n\
+            Button B: X+84, Y+37\n\
+            Prize: X=7870, Y=6450\n\
+            \n\
+            Button A: X+69, Y+23\n\
+            Button B: X+27, Y+71\n\
+            Prize: X=18641, Y=10279";
+        let puzzle = Day::create(input);
+        assert_eq!(puzzle.solve_part_2(), "875318608908");
+    }
+
+    #[test]
+    fn test_min_cost_parallel_buttons() {
+        let machine = |a, b, prize| ClawMachine { a, b, prize };
+        assert_eq!(machine((2, 2), (1, 1), (10, 10)).min_cost(None), Some(10));
+        assert_eq!(machine((4, 4), (1, 1), (10, 10)).min_cost(None), Some(8));
+        assert_eq!(machine((4, 4), (6, 6), (10, 10)).min_cost(None), Some(4));
+        assert_eq!(machine((4, 4), (6, 6), (11, 11)).min_cost(None), None);
+        assert_eq!(machine((4, 4), (6, 6), (10, 11)).min_cost(None), None);
+        assert_eq!(machine((0, 3), (0, 1), (0, 9)).min_cost(None), Some(9));
+        assert_eq!(machine((0, 0), (0, 0), (0, 0)).min_cost(None), Some(0));
+        assert_eq!(
+            machine((1, 1), (2, 2), (302, 302)).min_cost(Some(100)),
+            None
+        );
+        assert_eq!(
+            machine((1, 1), (2, 2), (250, 250)).min_cost(Some(100)),
+            Some(250)
+        );
+    }
+
+    #[test]
+    fn test_min_cost_matches_brute_force() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(13);
+        for _ in 0..2000 {
+            let a = (rng.random_range(0..10), rng.random_range(0..10));
+            let b = if rng.random_bool(0.5) {
+                (rng.random_range(0..10), rng.random_range(0..10))
+            } else {
+                let scale = rng.random_range(1..4);
+                (a.0 * scale, a.1 * scale)
+            };
+            let prize = (rng.random_range(0..300), rng.random_range(0..300));
+            let machine = ClawMachine { a, b, prize };
+            let brute_force = (0..=100)
+                .flat_map(|a| (0..=100).map(move |b| (a, b)))
+                .filter(|&(p, q)| a.0 * p + b.0 * q == prize.0 && a.1 * p + b.1 * q == prize.1)
+                .map(|(p, q)| 3 * p as i128 + q as i128)
+                .min();
+            assert_eq!(machine.min_cost(Some(100)), brute_force, "{:?}", machine);
+        }
+    }
+
+    /// Finds the cheapest way to win the prize with z3's optimizer, for cross-checking.
+    #[cfg(feature = "z3")]
+    fn min_cost_z3(machine: &ClawMachine, max_presses: Option<u64>) -> Option<i128> {
+        use std::ops::{Add, Mul};
+        use z3::ast::{Ast, Int};
+        use z3::{Config, Context, Optimize, SatResult};
+
+        let ctx = &Context::new(&Config::default());
+        let opt = Optimize::new(ctx);
+        let a = Int::new_const(ctx, "a");
+        let b = Int::new_const(ctx, "b");
+        let a_x = Int::from_u64(ctx, machine.a.0 as u64);
+        let a_y = Int::from_u64(ctx, machine.a.1 as u64);
+        let b_x = Int::from_u64(ctx, machine.b.0 as u64);
+        let b_y = Int::from_u64(ctx, machine.b.1 as u64);
+        let p_x = Int::from_u64(ctx, machine.prize.0 as u64);
+        let p_y = Int::from_u64(ctx, machine.prize.1 as u64);
+        let cost = a.clone().mul(&Int::from_u64(ctx, 3)).add(&b.clone());
+        let mut assumptions = vec![
+            a.clone().ge(&Int::from_u64(ctx, 0)),
+            b.clone().ge(&Int::from_u64(ctx, 0)),
+            a.clone().mul(&a_x).add(&b.clone().mul(&b_x))._eq(&p_x),
+            a.clone().mul(&a_y).add(&b.clone().mul(&b_y))._eq(&p_y),
+        ];
+        if let Some(max_presses) = max_presses {
+            assumptions.push(a.le(&Int::from_u64(ctx, max_presses)));
+            assumptions.push(b.le(&Int::from_u64(ctx, max_presses)));
+        }
+        opt.minimize(&cost);
+        if opt.check(&assumptions) == SatResult::Sat {
+            let model = opt.get_model().unwrap();
+            Some(model.eval(&cost, false).unwrap().as_u64().unwrap() as i128)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_min_cost_matches_z3() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(13);
+        for _ in 0..200 {
+            let a = (rng.random_range(1..100), rng.random_range(1..100));
+            let b = if rng.random_bool(0.5) {
+                (rng.random_range(1..100), rng.random_range(1..100))
+            } else {
+                let scale = rng.random_range(1..4);
+                (a.0 * scale, a.1 * scale)
+            };
+            let offset = if rng.random_bool(0.5) {
+                10000000000000
+            } else {
+                0
+            };
+            let prize = (
+                rng.random_range(0..20000) + offset,
+                rng.random_range(0..20000) + offset,
+            );
+            let machine = ClawMachine { a, b, prize };
+            assert_eq!(
+                machine.min_cost(Some(100)),
+                min_cost_z3(&machine, Some(100))
+            );
+            assert_eq!(machine.min_cost(None), min_cost_z3(&machine, None));
+        }
+    }
+
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/13")).unwrap();
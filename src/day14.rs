@@ -1,8 +1,19 @@
-use crate::puzzle::Puzzle;
+use crate::grid::{Grid, Point};
+use crate::number_theory::{crt, lcm};
+use crate::puzzle::{Puzzle, PuzzleParams};
 use lazy_regex::regex_captures;
+use std::cmp::Reverse;
+use std::fmt::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 pub struct Day {
     robots: Vec<Robot>,
+    width: u64,
+    height: u64,
+    scoring: Scoring,
+    render: RenderOptions,
 }
 
 impl Puzzle for Day {
@@ -11,18 +22,162 @@ impl Puzzle for Day {
     /// Time complexity: TODO
     /// Auxiliary space complexity: TODO
     fn solve_part_1(&self) -> String {
-        calculate_safety_factor(&self.robots, 101, 103, 100).to_string()
+        calculate_safety_factor(&self.robots, self.width, self.height, 100).to_string()
     }
 
-    /// TODO
+    /// We're asked for the fewest seconds until the robots arrange themselves into a picture of a
+    /// Christmas tree. See [`Scoring`] for the ways the tree's frame can be spotted.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O((w+h)*n) for variance scoring, or O(w*h*(n+w*h)) otherwise
+    /// Auxiliary space complexity: O(n+w*h)
     fn solve_part_2(&self) -> String {
-        find_xmas_tree(&self.robots, 101, 103).to_string()
+        match find_xmas_tree(&self.robots, self.width, self.height, self.scoring) {
+            Ok(time) => time.to_string(),
+            Err(e) => e,
+        }
+    }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        let RenderOptions {
+            time,
+            frames,
+            format,
+            ref dir,
+        } = self.render;
+        match mode {
+            "render" => Some(self.render_frame(time, format)),
+            "export" => {
+                let times = time..time + frames;
+                let result =
+                    export_frames(&self.robots, self.width, self.height, times, format, dir);
+                Some(match result {
+                    Ok(()) => format!("Wrote {} frames to {}", frames, dir.display()),
+                    Err(e) => format!("Failed to write frames to {}: {}", dir.display(), e),
+                })
+            }
+            "tree" => {
+                let time = match find_xmas_tree(&self.robots, self.width, self.height, self.scoring)
+                {
+                    Ok(time) => time,
+                    Err(e) => return Some(e),
+                };
+                let frame = self.render_frame(time, format);
+                match format {
+                    ImageFormat::Ascii => Some(format!("After {} seconds:\n{}", time, frame)),
+                    _ => Some(frame),
+                }
+            }
+            _ => None,
+        }
     }
 }
 
+impl Day {
+    fn render_frame(&self, time: u64, format: ImageFormat) -> String {
+        let positions = perform_move(&self.robots, self.width, self.height, time);
+        render(&positions, self.width, self.height, format)
+    }
+}
+
+/// Options for the debugging modes that draw the robots.
+struct RenderOptions {
+    time: u64,
+    frames: u64,
+    format: ImageFormat,
+    dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageFormat {
+    /// The puzzle's own notation: the number of robots on each tile, or `.` if there are none.
+    Ascii,
+    /// A plain portable bitmap, with a black pixel wherever there is at least one robot.
+    Pbm,
+    /// A plain portable graymap, where brighter pixels have more robots.
+    Pgm,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Ascii => "txt",
+            ImageFormat::Pbm => "pbm",
+            ImageFormat::Pgm => "pgm",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(ImageFormat::Ascii),
+            "pbm" => Ok(ImageFormat::Pbm),
+            "pgm" => Ok(ImageFormat::Pgm),
+            _ => Err(format!("Unknown image format: {}", s)),
+        }
+    }
+}
+
+/// Draws the robots at the given positions in the given format.
+fn render(positions: &[(u64, u64)], width: u64, height: u64, format: ImageFormat) -> String {
+    let mut counts = vec![vec![0u32; width as usize]; height as usize];
+    for &(x, y) in positions {
+        counts[y as usize][x as usize] += 1;
+    }
+    let mut output = String::new();
+    match format {
+        ImageFormat::Ascii => {}
+        ImageFormat::Pbm => writeln!(output, "P1\n{} {}", width, height).unwrap(),
+        ImageFormat::Pgm => {
+            let max = counts.iter().flatten().copied().max().unwrap_or(0).max(1);
+            writeln!(output, "P2\n{} {}\n{}", width, height, max).unwrap()
+        }
+    }
+    for row in &counts {
+        let line = match format {
+            ImageFormat::Ascii => row
+                .iter()
+                .map(|&count| match count {
+                    0 => '.',
+                    _ => char::from_digit(count, 10).unwrap_or('+'),
+                })
+                .collect::<String>(),
+            ImageFormat::Pbm => row
+                .iter()
+                .map(|&count| if count > 0 { "1" } else { "0" })
+                .collect::<Vec<_>>()
+                .join(" "),
+            ImageFormat::Pgm => row
+                .iter()
+                .map(|count| count.to_string())
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        writeln!(output, "{}", line).unwrap();
+    }
+    output
+}
+
+/// Writes one file per time in the range to the directory, named after the time it shows.
+fn export_frames(
+    robots: &[Robot],
+    width: u64,
+    height: u64,
+    times: Range<u64>,
+    format: ImageFormat,
+    dir: &Path,
+) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for time in times {
+        let positions = perform_move(robots, width, height, time);
+        let path = dir.join(format!("frame_{:05}.{}", time, format.extension()));
+        std::fs::write(path, render(&positions, width, height, format))?;
+    }
+    Ok(())
+}
+
 struct Robot {
     p: (i64, i64),
     v: (i64, i64),
@@ -63,53 +218,125 @@ fn calculate_safety_factor(robots: &[Robot], width: u64, height: u64, time: u64)
         .unwrap()
 }
 
-fn find_xmas_tree(robots: &[Robot], width: u64, height: u64) -> u64 {
-    let mut x_offset = 0;
-    let mut y_offset = 0;
-    for time in 0.. {
-        let positions = perform_move(robots, width, height, time);
-        let (dev_x, dev_y) = mean_absolute_deviation(&positions);
-        if dev_x < 0.35 {
-            x_offset = time;
-        }
-        if dev_y < 0.35 {
-            y_offset = time;
+/// How frames are scored when looking for the Christmas tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scoring {
+    /// The tree packs most of the robots into a small area, so it's the frame where the robots'
+    /// coordinates vary the least. The x and y coordinates repeat independently every `width` and
+    /// `height` seconds, so each axis is minimized on its own and the two are combined with the
+    /// Chinese remainder theorem.
+    Variance,
+    /// Most of the robots are in the tree, so it's the frame with the lowest safety factor.
+    SafetyFactor,
+    /// The tree has a frame around it, so it's the frame with the longest horizontal run of robots.
+    HorizontalRun,
+}
+
+impl FromStr for Scoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "variance" => Ok(Scoring::Variance),
+            "safety" => Ok(Scoring::SafetyFactor),
+            "run" => Ok(Scoring::HorizontalRun),
+            _ => Err(format!("Unknown scoring: {}", s)),
         }
-        if x_offset != 0 && y_offset != 0 {
-            break;
+    }
+}
+
+/// Finds the first time at which the robots form a Christmas tree. Every frame repeats after
+/// lcm(width, height) seconds, so at most that many frames are scored.
+fn find_xmas_tree(
+    robots: &[Robot],
+    width: u64,
+    height: u64,
+    scoring: Scoring,
+) -> Result<u64, String> {
+    if robots.is_empty() || width == 0 || height == 0 {
+        return Err("There are no robots to form a tree".to_string());
+    }
+    let period = lcm(width, height);
+    match scoring {
+        Scoring::Variance => {
+            let x_time = best_time(0..width, |time| {
+                let xs = perform_move(robots, width, height, time);
+                variance(xs.iter().map(|&(x, _)| x))
+            })?;
+            let y_time = best_time(0..height, |time| {
+                let ys = perform_move(robots, width, height, time);
+                variance(ys.iter().map(|&(_, y)| y))
+            })?;
+            crt(x_time, width, y_time, height)
+                .ok_or("The x and y coordinates never line up at the same time".to_string())
         }
+        Scoring::SafetyFactor => best_time(0..period, |time| {
+            calculate_safety_factor(robots, width, height, time)
+        }),
+        Scoring::HorizontalRun => best_time(0..period, |time| {
+            let positions = perform_move(robots, width, height, time);
+            Reverse(longest_horizontal_run(&positions, width, height))
+        }),
     }
-    for i in 0.. {
-        let time = x_offset + i * width;
-        if (time - y_offset).rem_euclid(height) == 0 {
-            return time;
+}
+
+/// Returns the time with the lowest score, or an error if no single time scores lowest.
+fn best_time<S: Ord>(times: Range<u64>, mut score: impl FnMut(u64) -> S) -> Result<u64, String> {
+    let mut best: Option<(u64, S)> = None;
+    let mut tied = false;
+    for time in times {
+        let score = score(time);
+        match &best {
+            Some((_, best_score)) if score > *best_score => {}
+            Some((_, best_score)) if score == *best_score => tied = true,
+            _ => {
+                best = Some((time, score));
+                tied = false;
+            }
         }
     }
-    unreachable!()
+    match best {
+        Some((time, _)) if !tied => Ok(time),
+        _ => Err("No frame stands out as the tree".to_string()),
+    }
+}
+
+/// Returns the variance of the values scaled by n^2, which keeps it an exact integer.
+fn variance(values: impl Iterator<Item = u64>) -> u128 {
+    let (mut n, mut sum, mut sum_squares) = (0u128, 0u128, 0u128);
+    for value in values {
+        n += 1;
+        sum += value as u128;
+        sum_squares += value as u128 * value as u128;
+    }
+    n * sum_squares - sum * sum
 }
 
-fn mean_absolute_deviation(positions: &[(u64, u64)]) -> (f64, f64) {
-    let mut mean_x = 0f64;
-    let mut mean_y = 0f64;
-    for (p_x, p_y) in positions {
-        mean_x += *p_x as f64;
-        mean_y += *p_y as f64;
+fn longest_horizontal_run(positions: &[(u64, u64)], width: u64, height: u64) -> usize {
+    let mut occupied = Grid::new(height as usize, width as usize, false);
+    for &(x, y) in positions {
+        occupied[Point::new(y as i32, x as i32)] = true;
     }
-    mean_x /= positions.len() as f64;
-    mean_y /= positions.len() as f64;
-    let mut dev_x = 0f64;
-    let mut dev_y = 0f64;
-    for (p_x, p_y) in positions {
-        dev_x += (*p_x as f64 - mean_x).abs();
-        dev_y += (*p_y as f64 - mean_y).abs();
+    let mut longest = 0;
+    let mut run = 0;
+    for (point, &robot) in occupied.iter() {
+        if point.col == 0 {
+            run = 0;
+        }
+        run = if robot { run + 1 } else { 0 };
+        longest = longest.max(run);
     }
-    dev_x /= mean_x * positions.len() as f64;
-    dev_y /= mean_y * positions.len() as f64;
-    (dev_x, dev_y)
+    longest
 }
 
 impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
+    /// Takes the parameters `width` and `height` (the size of the room, 101x103 by default) and
+    /// `scoring` (how to spot the tree: `variance`, `safety` or `run`, variance by default).
+    ///
+    /// The debugging modes also take `time` (the first frame to draw, 0 by default), `frames` (the
+    /// number of frames to export, 1 by default), `format` (`ascii`, `pbm` or `pgm`, ascii by
+    /// default) and `dir` (where to export frames to, `frames` by default).
+    pub fn create(input: &str, params: &PuzzleParams) -> Box<dyn Puzzle> {
         let robots = input
             .lines()
             .map(|line| {
@@ -121,7 +348,18 @@ impl Day {
                 }
             })
             .collect();
-        Box::new(Day { robots })
+        Box::new(Day {
+            robots,
+            width: params.get("width", 101),
+            height: params.get("height", 103),
+            scoring: params.get("scoring", Scoring::Variance),
+            render: RenderOptions {
+                time: params.get("time", 0),
+                frames: params.get("frames", 1),
+                format: params.get("format", ImageFormat::Ascii),
+                dir: params.get("dir", PathBuf::from("frames")),
+            },
+        })
     }
 }
 
@@ -186,17 +424,175 @@ mod tests {
         assert_eq!(safety_factor, 12);
     }
 
+    #[test]
+    fn test_part_1_example_2() {
+        let input = "\
+            p=0,4 v=3,-3\n\
+            p=6,3 v=-1,-3\n\
+            p=10,3 v=-1,2\n\
+            p=2,0 v=2,-1\n\
+            p=0,0 v=1,3\n\
+            p=3,0 v=-2,-2\n\
+            p=7,6 v=-1,-3\n\
+            p=3,0 v=-1,-2\n\
+            p=9,3 v=2,3\n\
+            p=7,3 v=-1,2\n\
+            p=2,4 v=2,-3\n\
+            p=9,5 v=-3,-3";
+        let params = PuzzleParams::default().with("width", 11).with("height", 7);
+        let puzzle = Day::create(input, &params);
+        assert_eq!(puzzle.solve_part_1(), "12");
+    }
+
+    #[test]
+    fn test_render_ascii() {
+        let input = "\
+            p=0,4 v=3,-3\n\
+            p=6,3 v=-1,-3\n\
+            p=10,3 v=-1,2\n\
+            p=2,0 v=2,-1\n\
+            p=0,0 v=1,3\n\
+            p=3,0 v=-2,-2\n\
+            p=7,6 v=-1,-3\n\
+            p=3,0 v=-1,-2\n\
+            p=9,3 v=2,3\n\
+            p=7,3 v=-1,2\n\
+            p=2,4 v=2,-3\n\
+            p=9,5 v=-3,-3";
+        let params = PuzzleParams::default()
+            .with("width", 11)
+            .with("height", 7)
+            .with("time", 100);
+        let puzzle = Day::create(input, &params);
+        let expected = "\
+            ......2..1.\n\
+            ...........\n\
+            1..........\n\
+            .11........\n\
+            .....1.....\n\
+            ...12......\n\
+            .1....1....\n";
+        assert_eq!(puzzle.inspect("render").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_render_images() {
+        let positions = vec![(0, 0), (2, 1), (2, 1)];
+        assert_eq!(
+            render(&positions, 3, 2, ImageFormat::Pbm),
+            "P1\n3 2\n1 0 0\n0 0 1\n"
+        );
+        assert_eq!(
+            render(&positions, 3, 2, ImageFormat::Pgm),
+            "P2\n3 2\n2\n1 0 0\n0 0 2\n"
+        );
+        assert_eq!("pgm".parse(), Ok(ImageFormat::Pgm));
+        assert!("png".parse::<ImageFormat>().is_err());
+    }
+
+    #[test]
+    fn test_export_frames() {
+        let input = "\
+            p=0,4 v=3,-3\n\
+            p=6,3 v=-1,-3\n\
+            p=10,3 v=-1,2\n\
+            p=2,0 v=2,-1\n\
+            p=0,0 v=1,3\n\
+            p=3,0 v=-2,-2\n\
+            p=7,6 v=-1,-3\n\
+            p=3,0 v=-1,-2\n\
+            p=9,3 v=2,3\n\
+            p=7,3 v=-1,2\n\
+            p=2,4 v=2,-3\n\
+            p=9,5 v=-3,-3";
+        let dir = tempfile::TempDir::new().unwrap();
+        let params = PuzzleParams::default()
+            .with("width", 11)
+            .with("height", 7)
+            .with("time", 99)
+            .with("frames", 2)
+            .with("format", "pbm")
+            .with("dir", dir.path().display());
+        let puzzle = Day::create(input, &params);
+        assert_eq!(
+            puzzle.inspect("export").unwrap(),
+            format!("Wrote 2 frames to {}", dir.path().display())
+        );
+        let mut files = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec!["frame_00099.pbm", "frame_00100.pbm"]);
+        let frame = std::fs::read_to_string(dir.path().join("frame_00100.pbm")).unwrap();
+        assert!(frame.starts_with("P1\n11 7\n0 0 0 0 0 0 1 0 0 1 0\n"));
+    }
+
+    /// Generates robots that gather in a block in the top-left quadrant at the given time, along
+    /// with some robots that wander around at random.
+    fn gathering_robots(width: u64, height: u64, time: u64) -> Vec<Robot> {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(14);
+        let mut robots = Vec::new();
+        for i in 0..140 {
+            let (x, y) = if i < 100 {
+                (2 + i % 25, 2 + i / 25)
+            } else {
+                (rng.random_range(0..width), rng.random_range(0..height))
+            };
+            let v: (i64, i64) = (rng.random_range(-50..50), rng.random_range(-50..50));
+            let p = (
+                (x as i64 - time as i64 * v.0).rem_euclid(width as i64),
+                (y as i64 - time as i64 * v.1).rem_euclid(height as i64),
+            );
+            robots.push(Robot { p, v });
+        }
+        robots
+    }
+
+    #[test]
+    fn test_find_xmas_tree() {
+        let robots = gathering_robots(61, 59, 1234);
+        for scoring in [
+            Scoring::Variance,
+            Scoring::SafetyFactor,
+            Scoring::HorizontalRun,
+        ] {
+            assert_eq!(find_xmas_tree(&robots, 61, 59, scoring), Ok(1234));
+        }
+    }
+
+    #[test]
+    fn test_find_xmas_tree_errors() {
+        let error = Err("There are no robots to form a tree".to_string());
+        assert_eq!(find_xmas_tree(&[], 11, 7, Scoring::Variance), error);
+        let still = vec![Robot {
+            p: (1, 1),
+            v: (0, 0),
+        }];
+        for scoring in [
+            Scoring::Variance,
+            Scoring::SafetyFactor,
+            Scoring::HorizontalRun,
+        ] {
+            assert_eq!(
+                find_xmas_tree(&still, 11, 7, scoring),
+                Err("No frame stands out as the tree".to_string())
+            );
+        }
+    }
+
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/14")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "232589280");
     }
 
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/14")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "7569");
     }
 }
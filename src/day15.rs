@@ -1,10 +1,15 @@
-use crate::puzzle::Puzzle;
+use crate::grid::{Direction, Grid, Point};
+use crate::puzzle::{Puzzle, PuzzleParams};
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
 
 pub struct Day {
-    grid: Vec<Vec<char>>,
-    moves: Vec<(i32, i32)>,
+    grid: Grid<char>,
+    moves: Vec<Direction>,
+    schedule: Schedule,
 }
 
 impl Puzzle for Day {
@@ -13,12 +18,10 @@ impl Puzzle for Day {
     /// Time complexity: TODO
     /// Auxiliary space complexity: TODO
     fn solve_part_1(&self) -> String {
-        let mut warehouse = Warehouse::create(&self.grid, 1);
-        for dir in &self.moves {
-            warehouse.move_robot(*dir);
+        match self.run(1) {
+            Ok(warehouse) => warehouse.gps_sum().to_string(),
+            Err(e) => e,
         }
-
-        warehouse.gps_sum().to_string()
     }
 
     /// TODO
@@ -26,11 +29,100 @@ impl Puzzle for Day {
     /// Time complexity: TODO
     /// Auxiliary space complexity: TODO
     fn solve_part_2(&self) -> String {
-        let mut warehouse = Warehouse::create(&self.grid, 2);
-        for dir in &self.moves {
-            warehouse.move_robot(*dir);
+        match self.run(2) {
+            Ok(warehouse) => warehouse.gps_sum().to_string(),
+            Err(e) => e,
+        }
+    }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        let scale = match mode {
+            "replay" => 1,
+            "replay-wide" => 2,
+            _ => return None,
+        };
+        let warehouse = match Warehouse::create(&self.grid, scale) {
+            Ok(warehouse) => warehouse,
+            Err(e) => return Some(e),
+        };
+        let robots = warehouse.robots.len();
+        let turns = match self.schedule.assign(&self.moves, robots) {
+            Ok(turns) => turns,
+            Err(e) => return Some(e),
+        };
+        let mut output = format!("Initial state:\n{}\n", warehouse);
+        for (&(robot, dir), state) in turns.iter().zip(warehouse.replay(&turns).skip(1)) {
+            let arrow = dir.arrow().unwrap();
+            if robots == 1 {
+                write!(output, "\nMove {}:\n{}\n", arrow, state).unwrap();
+            } else {
+                write!(output, "\nMove {} (robot {}):\n{}\n", arrow, robot, state).unwrap();
+            }
         }
-        warehouse.gps_sum().to_string()
+        Some(output.trim_end().to_string())
+    }
+}
+
+impl Day {
+    /// Returns the warehouse, stretched to `scale` tiles wide, after the robots have made every
+    /// move in the order given by the schedule.
+    fn run(&self, scale: i32) -> Result<Warehouse, String> {
+        let mut warehouse = Warehouse::create(&self.grid, scale)?;
+        for (robot, dir) in self.schedule.assign(&self.moves, warehouse.robots.len())? {
+            warehouse.move_robot(robot, dir);
+        }
+        Ok(warehouse)
+    }
+}
+
+/// The order in which the robots take turns making the moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Schedule {
+    /// With n robots, the i-th move is made by robot i mod n.
+    RoundRobin,
+    /// The robots take turns in the listed order, starting over at the end of the list, so `0,0,1`
+    /// lets robot 0 make two moves for each of robot 1's.
+    Cycle(Vec<usize>),
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "round-robin" {
+            return Ok(Schedule::RoundRobin);
+        }
+        let order = s
+            .split(',')
+            .map(|robot| robot.trim().parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Unknown schedule: {}", s))?;
+        Ok(Schedule::Cycle(order))
+    }
+}
+
+impl Schedule {
+    /// Assigns each of the moves to the robot that makes it.
+    fn assign(
+        &self,
+        moves: &[Direction],
+        robots: usize,
+    ) -> Result<Vec<(usize, Direction)>, String> {
+        if robots == 0 {
+            return Err("There are no robots in the warehouse".to_string());
+        }
+        let order = match self {
+            Schedule::RoundRobin => (0..robots).collect(),
+            Schedule::Cycle(order) => order.clone(),
+        };
+        if let Some(robot) = order.iter().find(|&&robot| robot >= robots) {
+            return Err(format!("There is no robot {} in the warehouse", robot));
+        }
+        Ok(order
+            .into_iter()
+            .cycle()
+            .zip(moves.iter().copied())
+            .collect())
     }
 }
 
@@ -46,87 +138,129 @@ impl Rectangle {
         Self { row, col, width }
     }
 
-    fn translate(&self, dir: (i32, i32)) -> Self {
+    fn translate(&self, dir: Direction) -> Self {
+        let (drow, dcol) = dir.delta();
         Self {
-            row: self.row + dir.0,
-            col: self.col + dir.1,
+            row: self.row + drow,
+            col: self.col + dcol,
             width: self.width,
         }
     }
 
-    fn occupying_points(&self) -> Vec<(i32, i32)> {
+    /// Returns the character drawn at the given offset into the rectangle: boxes one tile wide are
+    /// drawn as `O`, and wider boxes as `[]`, `[=]`, `[==]` and so on.
+    fn symbol(&self, offset: i32) -> char {
+        match (self.width, offset) {
+            (1, _) => 'O',
+            (_, 0) => '[',
+            (width, offset) if offset == width - 1 => ']',
+            _ => '=',
+        }
+    }
+
+    fn occupying_points(&self) -> Vec<Point> {
         let mut points = Vec::new();
         for c in self.col..(self.col + self.width) {
-            points.push((self.row, c));
+            points.push(Point::new(self.row, c));
         }
         points
     }
 }
 
+#[derive(Clone)]
 struct Warehouse {
-    walls: HashSet<(i32, i32)>,
-    boxes: HashMap<(i32, i32), Rectangle>,
-    robot: (i32, i32),
+    height: usize,
+    width: usize,
+    walls: HashSet<Point>,
+    boxes: HashMap<Point, Rectangle>,
+    robots: Vec<Point>,
 }
 
 impl Warehouse {
-    fn create(grid: &[Vec<char>], width: i32) -> Self {
+    /// Creates a warehouse from a map where every tile is stretched to `scale` tiles wide.
+    ///
+    /// Boxes may be drawn as `O` or in the already-widened `[]`, `[=]`, ... notation, so maps can
+    /// mix boxes of different sizes. Every `@` is a robot, numbered in reading order.
+    ///
+    /// Fails if the map has a `]` without a `[` earlier in the row, or the other way around.
+    fn create(grid: &Grid<char>, scale: i32) -> Result<Self, String> {
         let mut walls = HashSet::new();
         let mut boxes = HashMap::new();
-        let mut robot = (0, 0);
-        for (row, line) in grid.iter().enumerate() {
-            for (col, ch) in line.iter().enumerate() {
-                let col = width * col as i32;
-                match ch {
-                    '#' => {
-                        let rect = Rectangle::create(row as i32, col, width);
-                        for point in rect.occupying_points() {
-                            walls.insert(point);
-                        }
+        let mut robots = Vec::new();
+        let mut box_start: Option<(i32, i32)> = None;
+        for (point, &ch) in grid.iter() {
+            let (row, col) = (point.row, scale * point.col);
+            if let Some(start) = box_start
+                && (start.0 != row || !matches!(ch, '=' | ']'))
+            {
+                return Err(unmatched_bracket('[', start, scale));
+            }
+            match ch {
+                '#' => {
+                    let rect = Rectangle::create(row, col, scale);
+                    for point in rect.occupying_points() {
+                        walls.insert(point);
                     }
-                    '@' => {
-                        robot = (row as i32, col);
+                }
+                '@' => {
+                    robots.push(Point::new(row, col));
+                }
+                'O' => {
+                    let rect = Rectangle::create(row, col, scale);
+                    for point in rect.occupying_points() {
+                        boxes.insert(point, rect);
                     }
-                    'O' => {
-                        let rect = Rectangle::create(row as i32, col, width);
-                        for point in rect.occupying_points() {
-                            boxes.insert(point, rect);
-                        }
+                }
+                '[' => box_start = Some((row, col)),
+                ']' => {
+                    let Some((_, start)) = box_start.take() else {
+                        return Err(unmatched_bracket(']', (row, col), scale));
+                    };
+                    let rect = Rectangle::create(row, start, col + scale - start);
+                    for point in rect.occupying_points() {
+                        boxes.insert(point, rect);
                     }
-                    _ => {}
                 }
+                _ => {}
             }
         }
-        Self {
+        if let Some(start) = box_start {
+            return Err(unmatched_bracket('[', start, scale));
+        }
+        Ok(Self {
+            height: grid.height(),
+            width: grid.width() * scale as usize,
             walls,
             boxes,
-            robot,
-        }
+            robots,
+        })
     }
 
-    fn move_robot(&mut self, dir: (i32, i32)) {
+    /// Moves the robot one step, pushing any boxes in its way. Nothing moves if the robot or any of
+    /// the boxes it pushes would hit a wall or another robot.
+    fn move_robot(&mut self, robot: usize, dir: Direction) {
         let mut dependents: Vec<Rectangle> = Vec::new();
-        let mut queue: Vec<(i32, i32)> = Vec::new();
-        queue.push((self.robot.0 + dir.0, self.robot.1 + dir.1));
+        let mut queue: Vec<Point> = Vec::new();
+        queue.push(self.robots[robot].step(dir));
         while let Some(current) = queue.pop() {
-            if self.walls.contains(&current) {
+            if self.walls.contains(&current) || self.robots.contains(&current) {
                 return;
             }
             if let Some(rect) = self.boxes.get(&current) {
                 dependents.push(*rect);
                 match dir {
-                    (r, 0) => {
-                        for c in rect.col..(rect.col + rect.width) {
-                            queue.push((rect.row + r, c));
+                    Direction::North | Direction::South => {
+                        for point in rect.occupying_points() {
+                            queue.push(point.step(dir));
                         }
                     }
-                    (0, -1) => queue.push((rect.row, rect.col - 1)), // Left
-                    (0, 1) => queue.push((rect.row, rect.col + rect.width)), // Right
+                    Direction::West => queue.push(Point::new(rect.row, rect.col - 1)),
+                    Direction::East => queue.push(Point::new(rect.row, rect.col + rect.width)),
                     _ => unreachable!(),
                 }
             }
         }
-        self.robot = (self.robot.0 + dir.0, self.robot.1 + dir.1);
+        self.robots[robot] = self.robots[robot].step(dir);
         for rect in &dependents {
             for point in rect.occupying_points() {
                 self.boxes.remove(&point);
@@ -140,6 +274,15 @@ impl Warehouse {
         }
     }
 
+    /// Returns the warehouse as it is now, followed by the warehouse after each of the moves in the
+    /// schedule, where each move names the robot that makes it.
+    fn replay(mut self, schedule: &[(usize, Direction)]) -> impl Iterator<Item = Warehouse> + '_ {
+        std::iter::once(self.clone()).chain(schedule.iter().map(move |&(robot, dir)| {
+            self.move_robot(robot, dir);
+            self.clone()
+        }))
+    }
+
     fn gps_sum(&self) -> i32 {
         self.boxes
             .values()
@@ -151,28 +294,48 @@ impl Warehouse {
     }
 }
 
+/// Describes a box edge with no matching edge, at the given position in the widened map.
+fn unmatched_bracket(bracket: char, (row, col): (i32, i32), scale: i32) -> String {
+    format!(
+        "Unmatched {} at row {}, column {}",
+        bracket,
+        row,
+        col / scale
+    )
+}
+
+/// Renders the warehouse in the puzzle's notation.
+impl fmt::Display for Warehouse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut grid = Grid::new(self.height, self.width, '.');
+        for &wall in &self.walls {
+            grid[wall] = '#';
+        }
+        for (&point, rect) in &self.boxes {
+            grid[point] = rect.symbol(point.col - rect.col);
+        }
+        for &robot in &self.robots {
+            grid[robot] = '@';
+        }
+        write!(f, "{}", grid)
+    }
+}
+
 impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
+    pub fn create(input: &str, params: &PuzzleParams) -> Box<dyn Puzzle> {
         let mut parts = input.split("\n\n");
-        let grid = parts
-            .next()
-            .unwrap()
-            .lines()
-            .map(|line| line.chars().collect())
-            .collect();
+        let grid = Grid::parse(parts.next().unwrap());
         let moves = parts
             .next()
             .unwrap()
             .chars()
-            .filter_map(|dir| match dir {
-                '^' => Some((-1, 0)),
-                'v' => Some((1, 0)),
-                '<' => Some((0, -1)),
-                '>' => Some((0, 1)),
-                _ => None,
-            })
+            .filter_map(Direction::from_arrow)
             .collect();
-        Box::new(Day { grid, moves })
+        Box::new(Day {
+            grid,
+            moves,
+            schedule: params.get("schedule", Schedule::RoundRobin),
+        })
     }
 }
 
@@ -205,7 +368,7 @@ mod tests {
             <><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>\
             ^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>\
             v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "10092");
     }
 
@@ -222,14 +385,14 @@ mod tests {
             ########\n\
             \n\
             <^^>>>vv<v>>v<<";
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "2028");
     }
 
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/15")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "1511865");
     }
 
@@ -257,14 +420,298 @@ mod tests {
             <><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>\
             ^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>\
             v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^";
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "9021");
     }
 
+    #[test]
+    fn test_replay() {
+        let input = "\
+            ########\n\
+            #..O.O.#\n\
+            ##@.O..#\n\
+            #...O..#\n\
+            #.#.O..#\n\
+            #...O..#\n\
+            #......#\n\
+            ########\n\
+            \n\
+            <^^>>>vv<v>>v<<";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        let replay = puzzle.inspect("replay").unwrap();
+        let states = replay.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(states.len(), 16);
+        assert_eq!(
+            states[0],
+            "\
+            Initial state:\n\
+            ########\n\
+            #..O.O.#\n\
+            ##@.O..#\n\
+            #...O..#\n\
+            #.#.O..#\n\
+            #...O..#\n\
+            #......#\n\
+            ########"
+        );
+        assert_eq!(
+            states[5],
+            "\
+            Move >:\n\
+            ########\n\
+            #...@OO#\n\
+            ##..O..#\n\
+            #...O..#\n\
+            #.#.O..#\n\
+            #...O..#\n\
+            #......#\n\
+            ########"
+        );
+        assert_eq!(
+            states[15],
+            "\
+            Move <:\n\
+            ########\n\
+            #....OO#\n\
+            ##.....#\n\
+            #.....O#\n\
+            #.#O@..#\n\
+            #...O..#\n\
+            #...O..#\n\
+            ########"
+        );
+    }
+
+    #[test]
+    fn test_replay_wide() {
+        let input = "\
+            #######\n\
+            #...#.#\n\
+            #.....#\n\
+            #..OO@#\n\
+            #..O..#\n\
+            #.....#\n\
+            #######\n\
+            \n\
+            <vv<<^^<<^^";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        let replay = puzzle.inspect("replay-wide").unwrap();
+        let states = replay.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(states.len(), 12);
+        assert_eq!(
+            states[0],
+            "\
+            Initial state:\n\
+            ##############\n\
+            ##......##..##\n\
+            ##..........##\n\
+            ##....[][]@.##\n\
+            ##....[]....##\n\
+            ##..........##\n\
+            ##############"
+        );
+        assert_eq!(
+            states[7],
+            "\
+            Move ^:\n\
+            ##############\n\
+            ##......##..##\n\
+            ##...[][]...##\n\
+            ##....[]....##\n\
+            ##.....@....##\n\
+            ##..........##\n\
+            ##############"
+        );
+        assert_eq!(
+            states[11],
+            "\
+            Move ^:\n\
+            ##############\n\
+            ##...[].##..##\n\
+            ##...@.[]...##\n\
+            ##....[]....##\n\
+            ##..........##\n\
+            ##..........##\n\
+            ##############"
+        );
+    }
+
+    fn moves(arrows: &str) -> Vec<Direction> {
+        arrows.chars().filter_map(Direction::from_arrow).collect()
+    }
+
+    #[test]
+    fn test_three_wide_boxes() {
+        let grid = Grid::parse(
+            "\
+            #######\n\
+            #...#.#\n\
+            #.....#\n\
+            #..OO@#\n\
+            #..O..#\n\
+            #.....#\n\
+            #######",
+        );
+        let mut warehouse = Warehouse::create(&grid, 3).unwrap();
+        assert_eq!(
+            warehouse.to_string(),
+            "\
+            #####################\n\
+            ###.........###...###\n\
+            ###...............###\n\
+            ###......[=][=]@..###\n\
+            ###......[=]......###\n\
+            ###...............###\n\
+            #####################"
+        );
+        for dir in moves("<v<<<^") {
+            warehouse.move_robot(0, dir);
+        }
+        assert_eq!(
+            warehouse.to_string(),
+            "\
+            #####################\n\
+            ###.........###...###\n\
+            ###........[=]....###\n\
+            ###.....[=]@......###\n\
+            ###.....[=].......###\n\
+            ###...............###\n\
+            #####################"
+        );
+        assert_eq!(warehouse.gps_sum(), 211 + 308 + 408);
+    }
+
+    #[test]
+    fn test_mixed_box_sizes() {
+        let grid = Grid::parse(
+            "\
+            ########\n\
+            #......#\n\
+            #[=]...#\n\
+            #.O....#\n\
+            #.@O[]@#\n\
+            ########",
+        );
+        let mut warehouse = Warehouse::create(&grid, 1).unwrap();
+        // A robot in the way blocks a push just like a wall does.
+        warehouse.move_robot(1, Direction::West);
+        assert_eq!(warehouse.to_string(), grid.to_string());
+        warehouse.move_robot(0, Direction::North);
+        warehouse.move_robot(1, Direction::West);
+        // The wide box would hit the wall, so nothing moves.
+        warehouse.move_robot(0, Direction::North);
+        assert_eq!(
+            warehouse.to_string(),
+            "\
+            ########\n\
+            #[=]...#\n\
+            #.O....#\n\
+            #.@....#\n\
+            #.O[]@.#\n\
+            ########"
+        );
+    }
+
+    #[test]
+    fn test_multiple_robots() {
+        let input = "\
+            #######\n\
+            #@.O.@#\n\
+            #######\n\
+            \n\
+            >><<>";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        let replay = puzzle.inspect("replay").unwrap();
+        let states = replay.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(states[1], "Move > (robot 0):\n#######\n#.@O.@#\n#######");
+        assert_eq!(states[2], "Move > (robot 1):\n#######\n#.@O.@#\n#######");
+        assert_eq!(states[3], "Move < (robot 0):\n#######\n#@.O.@#\n#######");
+        assert_eq!(states[4], "Move < (robot 1):\n#######\n#@.O@.#\n#######");
+        assert_eq!(states[5], "Move > (robot 0):\n#######\n#.@O@.#\n#######");
+        assert_eq!(puzzle.solve_part_1(), "103");
+    }
+
+    #[test]
+    fn test_schedule() {
+        let input = "\
+            #######\n\
+            #@.O.@#\n\
+            #######\n\
+            \n\
+            >><<>";
+        let params = PuzzleParams::default().with("schedule", "0,0,1");
+        let puzzle = Day::create(input, &params);
+        let replay = puzzle.inspect("replay").unwrap();
+        let states = replay.split("\n\n").collect::<Vec<_>>();
+        assert_eq!(states[1], "Move > (robot 0):\n#######\n#.@O.@#\n#######");
+        assert_eq!(states[2], "Move > (robot 0):\n#######\n#..@O@#\n#######");
+        assert_eq!(states[3], "Move < (robot 1):\n#######\n#..@O@#\n#######");
+        assert_eq!(states[4], "Move < (robot 0):\n#######\n#.@.O@#\n#######");
+        assert_eq!(states[5], "Move > (robot 0):\n#######\n#..@O@#\n#######");
+        assert_eq!(puzzle.solve_part_1(), "104");
+
+        let params = PuzzleParams::default().with("schedule", "0,2");
+        let puzzle = Day::create(input, &params);
+        assert_eq!(
+            puzzle.solve_part_1(),
+            "There is no robot 2 in the warehouse"
+        );
+        assert_eq!(
+            "0, x".parse::<Schedule>(),
+            Err("Unknown schedule: 0, x".to_string())
+        );
+    }
+
+    #[test]
+    fn test_no_robots() {
+        let input = "#####\n#.O.#\n#####\n\n<>";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(
+            puzzle.solve_part_1(),
+            "There are no robots in the warehouse"
+        );
+        assert_eq!(
+            puzzle.solve_part_2(),
+            "There are no robots in the warehouse"
+        );
+        assert_eq!(
+            puzzle.inspect("replay"),
+            Some("There are no robots in the warehouse".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unmatched_brackets() {
+        let error = |map| Warehouse::create(&Grid::parse(map), 1).err();
+        assert_eq!(
+            error("#@.]#"),
+            Some("Unmatched ] at row 0, column 3".to_string())
+        );
+        assert_eq!(
+            error("#[@]#"),
+            Some("Unmatched [ at row 0, column 1".to_string())
+        );
+        assert_eq!(
+            error("#@.[\n]..#"),
+            Some("Unmatched [ at row 0, column 3".to_string())
+        );
+        assert_eq!(
+            error("#@.[="),
+            Some("Unmatched [ at row 0, column 3".to_string())
+        );
+        assert_eq!(
+            error("#[[]@"),
+            Some("Unmatched [ at row 0, column 1".to_string())
+        );
+        let input = "#@.][#\n\n<";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(puzzle.solve_part_2(), "Unmatched ] at row 0, column 3");
+    }
+
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/15")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "1519991");
     }
 }
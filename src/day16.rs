@@ -1,170 +1,282 @@
-use crate::puzzle::Puzzle;
+use crate::grid::{Direction, Grid, Point};
+use crate::puzzle::{Puzzle, PuzzleParams};
+use crate::search::{ShortestPaths, dijkstra};
 use itertools::Itertools;
-use petgraph::algo::dijkstra;
-use petgraph::graph::NodeIndex;
-use petgraph::prelude::EdgeRef;
-use petgraph::visit::Reversed;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::str::FromStr;
 
 pub struct Day {
-    start: NodeIndex,
-    end: Vec<NodeIndex>,
-    graph: petgraph::Graph<Vertex, i32>,
+    grid: Grid<char>,
+    start: Point,
+    end: Point,
+    facing: Direction,
+    costs: Costs,
+}
+
+/// What each of the reindeer's moves adds to its score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Costs {
+    step: usize,
+    turn: usize,
+    /// The cost of turning 180 degrees in one move, or None if the reindeer has to make two turns.
+    turn_around: Option<usize>,
 }
 
 impl Puzzle for Day {
-    /// TODO
+    /// We're given a maze and asked to find the lowest score a reindeer could get moving from the
+    /// start to the end, where stepping forward costs 1 point and turning 90 degrees costs 1000
+    /// (by default; see [`Day::create`]).
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// We search over (position, facing) states with Dijkstra's algorithm.
+    ///
+    /// Time complexity: O(n log n) where n is the number of cells in the maze
+    /// Auxiliary space complexity: O(n)
     fn solve_part_1(&self) -> String {
-        // Find the shortest path from start to end.
-        let paths = dijkstra(&self.graph, self.start, None, |e| *e.weight());
-        self.end
-            .iter()
-            .filter_map(|&end| paths.get(&end))
+        let paths = self.shortest_paths();
+        match self
+            .end_vertices()
+            .filter_map(|end| paths.distances.get(&end))
             .min()
-            .unwrap()
-            .to_string()
+        {
+            Some(score) => score.to_string(),
+            None => UNREACHABLE.to_string(),
+        }
     }
 
-    /// TODO
+    /// We're asked to count the tiles that lie on at least one of the lowest scoring paths.
+    ///
+    /// Dijkstra's algorithm records every predecessor on a cheapest path to each state, so we walk
+    /// those back from the cheapest end states.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n log n) where n is the number of cells in the maze
+    /// Auxiliary space complexity: O(n)
     fn solve_part_2(&self) -> String {
-        let dist_s = dijkstra(&self.graph, self.start, None, |e| *e.weight());
-        let &target_cost = self
-            .end
-            .iter()
-            .filter_map(|&end| dist_s.get(&end))
-            .min()
-            .unwrap();
-        let rev = Reversed(&self.graph);
-        let dist_e = self
-            .end
-            .iter()
-            .map(|&end| dijkstra(&rev, end, None, |e| *e.weight()))
-            .collect_vec();
-
-        // Find all nodes...
-        let mut winners = HashSet::new();
-        for v in self.graph.node_indices() {
-            if let Some(s_to_v) = dist_s.get(&v) {
-                for d in &dist_e {
-                    if let Some(v_to_e) = d.get(&v) {
-                        if s_to_v + v_to_e == target_cost {
-                            if let Some(vertex) = self.graph.node_weight(v) {
-                                winners.insert((vertex.row, vertex.col));
-                            }
-                        }
-                    }
-                }
+        match self.best_path_tiles().len() {
+            0 => UNREACHABLE.to_string(),
+            tiles => tiles.to_string(),
+        }
+    }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        match mode {
+            "paths" => Some(self.list_best_paths(MAX_LISTED_PATHS)),
+            "best" => Some(self.render_best_paths()),
+            _ => None,
+        }
+    }
+}
+
+/// The most best paths the `paths` mode lists, since there can be exponentially many.
+const MAX_LISTED_PATHS: usize = 100;
+
+const UNREACHABLE: &str = "The end can't be reached from the start";
+
+/// The direction the reindeer starts out facing, which has to be one of the four it can turn
+/// between.
+struct Facing(Direction);
+
+impl FromStr for Facing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let dir = s.parse::<Direction>()?;
+        if Direction::CARDINAL.contains(&dir) {
+            Ok(Facing(dir))
+        } else {
+            Err(format!("The reindeer can't start out facing {:?}", dir))
+        }
+    }
+}
+
+/// A single move of the reindeer between two vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Forward,
+    TurnClockwise,
+    TurnCounterClockwise,
+    TurnAround,
+}
+
+impl Action {
+    fn between(from: Vertex, to: Vertex) -> Self {
+        if from.position != to.position {
+            Action::Forward
+        } else if to.facing == from.facing.clockwise() {
+            Action::TurnClockwise
+        } else if to.facing == from.facing.counter_clockwise() {
+            Action::TurnCounterClockwise
+        } else {
+            Action::TurnAround
+        }
+    }
+}
+
+/// Describes a path as its starting vertex followed by its moves, with runs of steps forward
+/// grouped together, e.g. `Start at (13, 1) facing East, forward 2, turn counter-clockwise, ...`.
+fn describe(path: &[Vertex]) -> String {
+    let start = path[0];
+    let mut parts = vec![format!(
+        "Start at ({}, {}) facing {:?}",
+        start.position.row, start.position.col, start.facing
+    )];
+    let actions = path
+        .windows(2)
+        .map(|pair| Action::between(pair[0], pair[1]));
+    for (action, group) in &actions.chunk_by(|&action| action) {
+        match action {
+            Action::Forward => parts.push(format!("forward {}", group.count())),
+            Action::TurnClockwise => parts.extend(group.map(|_| "turn clockwise".to_string())),
+            Action::TurnCounterClockwise => {
+                parts.extend(group.map(|_| "turn counter-clockwise".to_string()))
             }
+            Action::TurnAround => parts.extend(group.map(|_| "turn around".to_string())),
         }
-        winners.len().to_string()
     }
+    parts.join(", ")
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 struct Vertex {
-    row: i32,
-    col: i32,
-    facing: (i32, i32),
+    position: Point,
+    facing: Direction,
 }
 
 impl Vertex {
     fn clockwise(&self) -> Self {
         Self {
-            row: self.row,
-            col: self.col,
-            facing: (self.facing.1, -self.facing.0),
+            position: self.position,
+            facing: self.facing.clockwise(),
         }
     }
 
     fn counter_clockwise(&self) -> Self {
         Self {
-            row: self.row,
-            col: self.col,
-            facing: (-self.facing.1, self.facing.0),
+            position: self.position,
+            facing: self.facing.counter_clockwise(),
+        }
+    }
+
+    fn reverse(&self) -> Self {
+        Self {
+            position: self.position,
+            facing: self.facing.reverse(),
         }
     }
 
     fn forward(&self) -> Self {
         Self {
-            row: self.row + self.facing.0,
-            col: self.col + self.facing.1,
+            position: self.position.step(self.facing),
             facing: self.facing,
         }
     }
 }
 
 impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
-        let grid = input
-            .lines()
-            .map(|line| line.chars().collect::<Vec<_>>())
-            .collect::<Vec<_>>();
-        let mut graph = petgraph::Graph::new();
-        let mut vertex_map = HashMap::new();
-        let mut add_vertex = |row: i32, col: i32| {
-            for &facing in &[(-1, 0), (1, 0), (0, -1), (0, 1)] {
-                let vertex = Vertex { row, col, facing };
-                let index = graph.add_node(vertex);
-                vertex_map.insert(vertex, index);
-            }
+    /// Takes the parameters `step` (the cost of a step forward, 1 by default), `turn` (the cost of
+    /// a 90 degree turn, 1000 by default), `turn_around` (the cost of a 180 degree turn, which isn't
+    /// allowed by default) and `facing` (the orthogonal direction the reindeer starts in, east by
+    /// default).
+    pub fn create(input: &str, params: &PuzzleParams) -> Box<dyn Puzzle> {
+        let grid = Grid::parse(input);
+        let start = grid.find('S').unwrap();
+        let end = grid.find('E').unwrap();
+        let costs = Costs {
+            step: params.get("step", 1),
+            turn: params.get("turn", 1000),
+            turn_around: params.get_optional("turn_around"),
         };
-        let mut start = (0, 0);
-        let mut end = (0, 0);
-        for (row, line) in grid.iter().enumerate() {
-            for (col, ch) in line.iter().enumerate() {
-                match ch {
-                    'S' => {
-                        start = (row as i32, col as i32);
-                        add_vertex(start.0, start.1);
-                    }
-                    'E' => {
-                        end = (row as i32, col as i32);
-                        add_vertex(end.0, end.1);
-                    }
-                    '.' => {
-                        add_vertex(row as i32, col as i32);
-                    }
-                    _ => {}
-                }
-            }
+        Box::new(Day {
+            grid,
+            start,
+            end,
+            facing: params.get("facing", Facing(Direction::East)).0,
+            costs,
+        })
+    }
+
+    fn shortest_paths(&self) -> ShortestPaths<Vertex> {
+        let start = Vertex {
+            position: self.start,
+            facing: self.facing,
+        };
+        dijkstra([start], |vertex| self.successors(vertex))
+    }
+
+    fn successors(&self, vertex: Vertex) -> Vec<(Vertex, usize)> {
+        let mut successors = vec![
+            (vertex.clockwise(), self.costs.turn),
+            (vertex.counter_clockwise(), self.costs.turn),
+        ];
+        if let Some(cost) = self.costs.turn_around {
+            successors.push((vertex.reverse(), cost));
         }
+        let forward = vertex.forward();
+        if self.grid.get(forward.position).is_some_and(|&ch| ch != '#') {
+            successors.push((forward, self.costs.step));
+        }
+        successors
+    }
 
-        // Handle facing edges.
-        for (vertex, &src) in &vertex_map {
-            // Clockwise turn.
-            if let Some(&dst) = vertex_map.get(&vertex.clockwise()) {
-                graph.add_edge(src, dst, 1000);
-            }
-            if let Some(&dst) = vertex_map.get(&vertex.counter_clockwise()) {
-                graph.add_edge(src, dst, 1000);
-            }
+    /// Returns the end vertices that are reached with the lowest score.
+    fn best_ends(&self, paths: &ShortestPaths<Vertex>) -> Vec<Vertex> {
+        let best = self
+            .end_vertices()
+            .filter_map(|end| paths.distances.get(&end))
+            .min();
+        self.end_vertices()
+            .filter(|end| paths.distances.get(end) == best)
+            .collect()
+    }
+
+    /// Returns up to `limit` of the lowest scoring paths through the maze.
+    fn best_paths(&self, limit: usize) -> Vec<Vec<Vertex>> {
+        let paths = self.shortest_paths();
+        paths.paths_to(self.best_ends(&paths), limit)
+    }
+
+    /// Returns every tile that lies on at least one of the lowest scoring paths.
+    fn best_path_tiles(&self) -> HashSet<Point> {
+        let paths = self.shortest_paths();
+        paths
+            .nodes_on_paths_to(self.best_ends(&paths))
+            .into_iter()
+            .map(|vertex| vertex.position)
+            .collect()
+    }
+
+    /// Describes up to `limit` of the lowest scoring paths move by move, noting when there are more
+    /// paths than that.
+    fn list_best_paths(&self, limit: usize) -> String {
+        let paths = self.best_paths(limit.saturating_add(1));
+        if paths.is_empty() {
+            return UNREACHABLE.to_string();
         }
+        let mut output = paths
+            .iter()
+            .take(limit)
+            .map(|path| describe(path))
+            .join("\n");
+        if paths.len() > limit {
+            write!(output, "\n(only the first {} paths are listed)", limit).unwrap();
+        }
+        output
+    }
 
-        // Handle movement edges.
-        for (vertex, &src) in &vertex_map {
-            if let Some(&dst) = vertex_map.get(&vertex.forward()) {
-                graph.add_edge(src, dst, 1);
-            }
+    /// Draws the maze with every tile on a lowest scoring path marked with an `O`.
+    fn render_best_paths(&self) -> String {
+        let mut grid = self.grid.clone();
+        for tile in self.best_path_tiles() {
+            grid[tile] = 'O';
         }
+        grid.to_string()
+    }
 
-        Box::new(Day {
-            start: vertex_map[&Vertex {
-                row: start.0,
-                col: start.1,
-                facing: (0, 1),
-            }],
-            end: vertex_map
-                .iter()
-                .filter(|v| v.0.row == end.0 && v.0.col == end.1)
-                .map(|(_, &index)| index)
-                .collect::<Vec<_>>(),
-            graph,
+    /// The end tile may be reached facing any direction.
+    fn end_vertices(&self) -> impl Iterator<Item = Vertex> + '_ {
+        Direction::CARDINAL.into_iter().map(|facing| Vertex {
+            position: self.end,
+            facing,
         })
     }
 }
@@ -192,7 +304,7 @@ mod tests {
             #.###.#.#.#.#.#\n\
             #S..#.....#...#\n\
             ###############";
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "7036");
     }
 
@@ -216,14 +328,14 @@ mod tests {
             #.#.#.#########.#\n\
             #S#.............#\n\
             #################";
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "11048");
     }
 
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/16")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "93436");
     }
 
@@ -245,7 +357,7 @@ mod tests {
             #.###.#.#.#.#.#\n\
             #S..#.....#...#\n\
             ###############";
-        let puzzle = Day::create(input);
+        let puzzle = Day::create(input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "45");
     }
 
@@ -269,14 +381,179 @@ mod tests {
             #.#.#.#########.#\n\
             #S#.............#\n\
             #################";
-        let puzzle = Day::create(input);
+        let puzzle = Day::create(input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "64");
     }
 
+    #[test]
+    fn test_best_paths() {
+        let input = "\
+            ######\n\
+            #...E#\n\
+            #.##.#\n\
+            #S...#\n\
+            ######";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(
+            puzzle.inspect("paths").unwrap(),
+            "Start at (3, 1) facing East, forward 3, turn counter-clockwise, forward 2"
+        );
+        assert_eq!(
+            puzzle.inspect("best").unwrap(),
+            "\
+            ######\n\
+            #...O#\n\
+            #.##O#\n\
+            #OOOO#\n\
+            ######"
+        );
+    }
+
+    #[test]
+    fn test_costs() {
+        let input = "\
+            ######\n\
+            #...E#\n\
+            #.##.#\n\
+            #S...#\n\
+            ######";
+        let score = |params: PuzzleParams| Day::create(input, &params).solve_part_1();
+        assert_eq!(score(PuzzleParams::default().with("turn", 1)), "6");
+        assert_eq!(score(PuzzleParams::default().with("step", 10)), "1050");
+        assert_eq!(
+            score(PuzzleParams::default().with("facing", "north")),
+            "1005"
+        );
+        assert_eq!(
+            score(PuzzleParams::default().with("facing", "west")),
+            "2005"
+        );
+        let params = PuzzleParams::default()
+            .with("facing", "west")
+            .with("turn_around", 0);
+        assert_eq!(score(params), "1005");
+
+        // Free turns make every route around the wall equally good.
+        let params = PuzzleParams::default()
+            .with("turn", 0)
+            .with("turn_around", 0);
+        let puzzle = Day::create(input, &params);
+        assert_eq!(puzzle.solve_part_1(), "5");
+        assert_eq!(puzzle.solve_part_2(), "10");
+        let paths = puzzle.inspect("paths").unwrap();
+        assert!(paths.contains("Start at (3, 1) facing East, turn around, "));
+    }
+
+    #[test]
+    fn test_unreachable_end() {
+        let input = "\
+            #####\n\
+            #S#E#\n\
+            #####";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(puzzle.solve_part_1(), UNREACHABLE);
+        assert_eq!(puzzle.solve_part_2(), UNREACHABLE);
+        assert_eq!(puzzle.inspect("paths").unwrap(), UNREACHABLE);
+    }
+
+    #[test]
+    fn test_facing_must_be_cardinal() {
+        assert_eq!(
+            "south".parse::<Facing>().map(|facing| facing.0),
+            Ok(Direction::South)
+        );
+        assert_eq!(
+            "northeast".parse::<Facing>().err(),
+            Some("The reindeer can't start out facing NorthEast".to_string())
+        );
+        assert_eq!(
+            "up".parse::<Facing>().err(),
+            Some("Unknown direction: up".to_string())
+        );
+    }
+
+    #[test]
+    fn test_best_paths_example_1() {
+        let input = "\
+            ###############\n\
+            #.......#....E#\n\
+            #.#.###.#.###.#\n\
+            #.....#.#...#.#\n\
+            #.###.#####.#.#\n\
+            #.#.#.......#.#\n\
+            #.#.#####.###.#\n\
+            #...........#.#\n\
+            ###.#.#####.#.#\n\
+            #...#.....#.#.#\n\
+            #.#.#.###.#.#.#\n\
+            #.....#...#.#.#\n\
+            #.###.#.#.#.#.#\n\
+            #S..#.....#...#\n\
+            ###############";
+        let puzzle = Day::create(input, &PuzzleParams::default());
+        assert_eq!(
+            puzzle.inspect("best").unwrap(),
+            "\
+            ###############\n\
+            #.......#....O#\n\
+            #.#.###.#.###O#\n\
+            #.....#.#...#O#\n\
+            #.###.#####.#O#\n\
+            #.#.#.......#O#\n\
+            #.#.#####.###O#\n\
+            #..OOOOOOOOO#O#\n\
+            ###O#O#####O#O#\n\
+            #OOO#O....#O#O#\n\
+            #O#O#O###.#O#O#\n\
+            #OOOOO#...#O#O#\n\
+            #O###.#.#.#O#O#\n\
+            #O..#.....#OOO#\n\
+            ###############"
+        );
+
+        let day = Day {
+            grid: Grid::parse(input),
+            start: Point::new(13, 1),
+            end: Point::new(1, 13),
+            facing: Direction::East,
+            costs: Costs {
+                step: 1,
+                turn: 1000,
+                turn_around: None,
+            },
+        };
+        let paths = day.best_paths(usize::MAX);
+        assert!(paths.len() > 1);
+        let listed = day.list_best_paths(paths.len());
+        assert_eq!(listed.lines().count(), paths.len());
+        assert!(!listed.contains("only the first"));
+        let listed = day.list_best_paths(paths.len() - 1);
+        assert_eq!(listed.lines().count(), paths.len());
+        assert!(listed.ends_with(&format!(
+            "(only the first {} paths are listed)",
+            paths.len() - 1
+        )));
+        let mut tiles = HashSet::new();
+        for path in &paths {
+            assert_eq!(path.first().unwrap().position, day.start);
+            assert_eq!(path.last().unwrap().position, day.end);
+            let score = path
+                .windows(2)
+                .map(|pair| match Action::between(pair[0], pair[1]) {
+                    Action::Forward => 1,
+                    _ => 1000,
+                })
+                .sum::<usize>();
+            assert_eq!(score, 7036);
+            tiles.extend(path.iter().map(|vertex| vertex.position));
+        }
+        assert_eq!(tiles.len(), 45);
+    }
+
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/16")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "486");
     }
 }
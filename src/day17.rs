@@ -1,12 +1,16 @@
 use crate::puzzle::Puzzle;
+use itertools::Itertools;
 use lazy_regex::regex_captures;
+use std::fmt;
+#[cfg(feature = "z3")]
 use z3::ast::{Ast, BV};
+#[cfg(feature = "z3")]
 use z3::{Context, Optimize};
 
 pub struct Day {
-    a: i32,
-    b: i32,
-    c: i32,
+    a: i64,
+    b: i64,
+    c: i64,
     program: Vec<i32>,
 }
 
@@ -17,7 +21,7 @@ impl Puzzle for Day {
     /// Auxiliary space complexity: TODO
     fn solve_part_1(&self) -> String {
         let mut vm = ClassicVm::new(self.a, self.b, self.c, self.program.clone());
-        let output = vm.run();
+        let output = vm.run().unwrap();
         output
             .iter()
             .map(|x| x.to_string())
@@ -25,73 +29,394 @@ impl Puzzle for Day {
             .join(",")
     }
 
-    /// TODO
+    /// We're given a program for a 3-bit computer and asked to find the smallest value of register
+    /// A that makes the program output a copy of itself. Programs that consume A three bits per
+    /// loop iteration are solved by searching backwards through A's octal digits; anything else
+    /// falls back to the symbolic solver, or to trying small values of A without z3.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n^2) VM instructions per octal digit tried
+    /// Auxiliary space complexity: O(n)
     fn solve_part_2(&self) -> String {
-        let ctx = Context::new(&z3::Config::default());
-        let mut vm = SymbolicVm::new(&ctx, self.b, self.c, self.program.clone());
-        vm.run()
+        match self.lowest_quine() {
+            Ok(a) => a.to_string(),
+            Err(e) => e,
+        }
+    }
+
+    fn inspect(&self, mode: &str) -> Option<String> {
+        match mode {
+            "disassemble" => Some(disassemble(&self.program).join("\n")),
+            "decompile" => Some(decompile(&self.program)),
+            "trace" => {
+                let mut vm = ClassicVm::new(self.a, self.b, self.c, self.program.clone());
+                Some(match vm.trace(CYCLE_BUDGET) {
+                    Ok(trace) => trace.iter().map(|step| step.to_string()).join("\n"),
+                    Err(e) => e,
+                })
+            }
+            "replay" => Some(match self.lowest_quine() {
+                Ok(a) => self.replay(a),
+                Err(e) => e,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The number of instructions a program may execute before it's assumed not to halt.
+const CYCLE_BUDGET: usize = 1_000_000;
+
+/// The values of A that are tried one by one for programs the octal search can't solve, along
+/// with the number of instructions each attempt may execute.
+const BRUTE_FORCE_LIMIT: i64 = 1 << 16;
+const BRUTE_FORCE_CYCLES: usize = 1_000;
+
+/// The number of instructions the symbolic solver may unroll before it gives up. A quine only
+/// takes a handful of instructions per output, so this is far more than any real program needs.
+#[cfg(feature = "z3")]
+const SYMBOLIC_CYCLES: usize = 10_000;
+
+/// A snapshot of the VM taken just before an instruction executes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TraceStep {
+    ip: i32,
+    opcode: i32,
+    operand: i32,
+    a: i64,
+    b: i64,
+    c: i64,
+}
+
+impl fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ip={:>2} {} {} A={} B={} C={}",
+            self.ip, MNEMONICS[self.opcode as usize], self.operand, self.a, self.b, self.c
+        )
     }
 }
 
+/// The reason the VM stopped running.
+#[derive(Debug, PartialEq, Eq)]
+enum Stop {
+    Halted,
+    Breakpoint(i32),
+}
+
 struct ClassicVm {
     ip: i32,
-    a: i32,
-    b: i32,
-    c: i32,
+    a: i64,
+    b: i64,
+    c: i64,
     program: Vec<i32>,
+    output: Vec<i32>,
 }
 
 impl ClassicVm {
-    fn new(a: i32, b: i32, c: i32, program: Vec<i32>) -> Self {
+    fn new(a: i64, b: i64, c: i64, program: Vec<i32>) -> Self {
         ClassicVm {
             ip: 0,
             a,
             b,
             c,
             program,
+            output: Vec::new(),
         }
     }
 
-    fn combo(&self, x: i32) -> i32 {
+    fn combo(&self, x: i32) -> Result<i64, String> {
         match x {
-            0..=3 => x,
-            4 => self.a,
-            5 => self.b,
-            6 => self.c,
-            _ => unreachable!(),
+            0..=3 => Ok(x as i64),
+            4 => Ok(self.a),
+            5 => Ok(self.b),
+            6 => Ok(self.c),
+            _ => Err(format!("Invalid combo operand {} at ip={}", x, self.ip)),
         }
     }
 
-    fn run(&mut self) -> Vec<i32> {
-        let mut output = Vec::new();
-        while self.ip >= 0 && self.ip < self.program.len() as i32 {
-            let opcode = self.program[self.ip as usize];
-            let operand = self.program[self.ip as usize + 1];
+    /// Divides A by 2 to the power of the combo operand. Powers of 64 or more leave nothing of A,
+    /// and negative powers are an error.
+    fn divide(&self, operand: i32) -> Result<i64, String> {
+        let power = self.combo(operand)?;
+        match power {
+            ..0 => Err(format!(
+                "Cannot divide by 2 to the power of {} at ip={}",
+                power, self.ip
+            )),
+            0..64 => Ok(self.a >> power),
+            _ => Ok(0),
+        }
+    }
+
+    /// The program halts once the ip no longer points at a complete instruction, including an
+    /// opcode at the very end of the program that has no operand after it.
+    fn halted(&self) -> bool {
+        self.ip < 0 || self.ip + 1 >= self.program.len() as i32
+    }
+
+    /// Executes a single instruction and returns the state of the VM before it ran, or None if
+    /// the program has already halted.
+    fn step(&mut self) -> Result<Option<TraceStep>, String> {
+        if self.halted() {
+            return Ok(None);
+        }
+        let opcode = self.program[self.ip as usize];
+        let operand = self.program[self.ip as usize + 1];
+        let step = TraceStep {
+            ip: self.ip,
+            opcode,
+            operand,
+            a: self.a,
+            b: self.b,
+            c: self.c,
+        };
+        match opcode {
+            /*adv*/ 0 => self.a = self.divide(operand)?,
+            /*bxl*/ 1 => self.b ^= operand as i64,
+            /*bst*/ 2 => self.b = self.combo(operand)? % 8,
+            /*jnz*/
+            3 => {
+                if self.a != 0 {
+                    self.ip = operand - 2;
+                }
+            }
+            /*bxc*/ 4 => self.b ^= self.c,
+            /*out*/ 5 => self.output.push((self.combo(operand)? % 8) as i32),
+            /*bdv*/ 6 => self.b = self.divide(operand)?,
+            /*cdv*/ 7 => self.c = self.divide(operand)?,
+            _ => return Err(format!("Invalid opcode {} at ip={}", opcode, self.ip)),
+        }
+        self.ip += 2;
+        Ok(Some(step))
+    }
+
+    /// Runs the program to completion and returns its output.
+    fn run(&mut self) -> Result<Vec<i32>, String> {
+        self.resume(&[], CYCLE_BUDGET)?;
+        Ok(self.output.clone())
+    }
+
+    /// Runs the program until it halts or is about to execute an instruction at one of the
+    /// breakpoints. The instruction at the current ip is always executed, so resuming from a
+    /// breakpoint makes progress.
+    fn resume(&mut self, breakpoints: &[i32], max_cycles: usize) -> Result<Stop, String> {
+        for cycle in 0..max_cycles {
+            if cycle > 0 && breakpoints.contains(&self.ip) {
+                return Ok(Stop::Breakpoint(self.ip));
+            }
+            if self.step()?.is_none() {
+                return Ok(Stop::Halted);
+            }
+        }
+        if self.halted() {
+            Ok(Stop::Halted)
+        } else {
+            Err(format!("Program did not halt within {} cycles", max_cycles))
+        }
+    }
+
+    /// Runs the program to completion, recording the state of the VM before every instruction.
+    fn trace(&mut self, max_cycles: usize) -> Result<Vec<TraceStep>, String> {
+        let mut trace = Vec::new();
+        for _ in 0..max_cycles {
+            match self.step()? {
+                Some(step) => trace.push(step),
+                None => return Ok(trace),
+            }
+        }
+        if self.halted() {
+            Ok(trace)
+        } else {
+            Err(format!("Program did not halt within {} cycles", max_cycles))
+        }
+    }
+}
+
+const MNEMONICS: [&str; 8] = ["adv", "bxl", "bst", "jnz", "bxc", "out", "bdv", "cdv"];
+
+/// Renders a combo operand as either its literal value or the register it refers to.
+fn combo_name(operand: i32) -> String {
+    match operand {
+        0..=3 => operand.to_string(),
+        4 => "A".to_string(),
+        5 => "B".to_string(),
+        6 => "C".to_string(),
+        _ => "?".to_string(),
+    }
+}
+
+/// Renders each instruction of the program as `ip: mnemonic operand`, with combo operands
+/// resolved to literals or registers. Opcodes the VM doesn't know, and a trailing opcode without an
+/// operand, are rendered as `??? opcode`.
+fn disassemble(program: &[i32]) -> Vec<String> {
+    program
+        .chunks(2)
+        .enumerate()
+        .map(|(i, instruction)| {
+            let opcode = instruction[0];
+            let (Some(&operand), Some(&mnemonic)) = (
+                instruction.get(1),
+                usize::try_from(opcode)
+                    .ok()
+                    .and_then(|opcode| MNEMONICS.get(opcode)),
+            ) else {
+                return format!("{:>2}: ??? {}", 2 * i, opcode);
+            };
             match opcode {
-                /*adv*/ 0 => self.a >>= self.combo(operand),
-                /*bxl*/ 1 => self.b ^= operand,
-                /*bst*/ 2 => self.b = self.combo(operand) % 8,
-                /*jnz*/
-                3 => {
-                    if self.a != 0 {
-                        self.ip = operand - 2;
-                    }
+                /*bxl, jnz*/ 1 | 3 => format!("{:>2}: {} {}", 2 * i, mnemonic, operand),
+                /*bxc*/ 4 => format!("{:>2}: {}", 2 * i, mnemonic),
+                _ => format!("{:>2}: {} {}", 2 * i, mnemonic, combo_name(operand)),
+            }
+        })
+        .collect()
+}
+
+/// Translates a single instruction into a pseudocode statement.
+fn statement(opcode: i32, operand: i32) -> String {
+    let combo = combo_name(operand);
+    match opcode {
+        /*adv*/ 0 => format!("A = A >> {}", combo),
+        /*bxl*/ 1 => format!("B = B ^ {}", operand),
+        /*bst*/ 2 => format!("B = {} % 8", combo),
+        /*jnz*/ 3 => format!("if A != 0 goto {}", operand),
+        /*bxc*/ 4 => "B = B ^ C".to_string(),
+        /*out*/ 5 => format!("out({} % 8)", combo),
+        /*bdv*/ 6 => format!("B = A >> {}", combo),
+        /*cdv*/ 7 => format!("C = A >> {}", combo),
+        _ => format!("??? {}", opcode),
+    }
+}
+
+/// If the program is a single loop that ends in `jnz 0` and shifts A right by a literal amount
+/// exactly once per iteration, returns the size of that shift.
+fn loop_shift(program: &[i32]) -> Option<i32> {
+    if !program.len().is_multiple_of(2) {
+        return None;
+    }
+    let instructions = program.chunks_exact(2).collect::<Vec<_>>();
+    let (last, body) = instructions.split_last()?;
+    if **last != [3, 0] || body.iter().any(|instruction| instruction[0] == 3) {
+        return None;
+    }
+    let shifts = body
+        .iter()
+        .filter(|instruction| instruction[0] == 0)
+        .map(|instruction| instruction[1])
+        .collect::<Vec<_>>();
+    match shifts[..] {
+        [shift] if (0..=3).contains(&shift) => Some(shift),
+        _ => None,
+    }
+}
+
+/// Returns true if the program is a single loop that shifts A right by 3 bits, outputs exactly one
+/// value per iteration and derives B and C afresh every iteration, which means each output only
+/// depends on the remaining octal digits of A.
+fn is_octal_loop(program: &[i32]) -> bool {
+    let outputs = program
+        .chunks_exact(2)
+        .filter(|instruction| instruction[0] == 5)
+        .count();
+    loop_shift(program) == Some(3) && outputs == 1 && resets_b_and_c(program)
+}
+
+/// Returns true if the program never reads B or C before it has written them, so that their
+/// values don't carry over from one iteration of a loop to the next.
+fn resets_b_and_c(program: &[i32]) -> bool {
+    let (mut b_written, mut c_written) = (false, false);
+    for instruction in program.chunks_exact(2) {
+        let (opcode, operand) = (instruction[0], instruction[1]);
+        let (reads_b, reads_c) = match opcode {
+            /*bxl*/ 1 => (true, false),
+            /*jnz*/ 3 => (false, false),
+            /*bxc*/ 4 => (true, true),
+            _ => (operand == 5, operand == 6),
+        };
+        if (reads_b && !b_written) || (reads_c && !c_written) {
+            return false;
+        }
+        match opcode {
+            /*bxl, bst, bxc, bdv*/ 1 | 2 | 4 | 6 => b_written = true,
+            /*cdv*/ 7 => c_written = true,
+            _ => {}
+        }
+    }
+    true
+}
+
+/// Finds the smallest value of A for which the program outputs itself. The last output only
+/// depends on the most significant octal digit of A, so we pick digits from most to least
+/// significant, keeping a digit only if the VM then reproduces the corresponding suffix of the
+/// program. Trying digits in ascending order means the first complete match is the smallest.
+fn find_quine(b: i64, c: i64, program: &[i32]) -> Option<i64> {
+    fn search(b: i64, c: i64, program: &[i32], index: usize, prefix: i64) -> Option<i64> {
+        for digit in 0..8 {
+            let a = (prefix << 3) | digit;
+            let mut vm = ClassicVm::new(a, b, c, program.to_vec());
+            if vm.run().is_ok_and(|output| output == program[index..]) {
+                if index == 0 {
+                    return Some(a);
+                }
+                if let Some(a) = search(b, c, program, index - 1, a) {
+                    return Some(a);
                 }
-                /*bxc*/ 4 => self.b ^= self.c,
-                /*out*/ 5 => output.push(self.combo(operand) % 8),
-                /*bdv*/ 6 => self.b = self.a >> self.combo(operand),
-                /*cdv*/ 7 => self.c = self.a >> self.combo(operand),
-                _ => unreachable!(),
             }
-            self.ip += 2;
         }
-        output
+        None
     }
+    search(b, c, program, program.len().checked_sub(1)?, 0)
+}
+
+/// Finds the smallest value of A below the limit for which the program outputs itself by running
+/// the program with every value in turn, giving up on a run as soon as its output goes astray.
+fn brute_force_quine(b: i64, c: i64, program: &[i32], limit: i64) -> Option<i64> {
+    let outputs = (0..program.len() as i32)
+        .step_by(2)
+        .filter(|&ip| program[ip as usize] == 5)
+        .collect::<Vec<_>>();
+    (0..limit).find(|&a| {
+        let mut vm = ClassicVm::new(a, b, c, program.to_vec());
+        loop {
+            match vm.resume(&outputs, BRUTE_FORCE_CYCLES) {
+                Ok(Stop::Breakpoint(_)) if program.starts_with(&vm.output) => {}
+                Ok(Stop::Halted) => return vm.output == program,
+                _ => return false,
+            }
+        }
+    })
 }
 
+/// Emits pseudocode for the program. Programs with the usual "loop while A != 0" shape are
+/// rendered as a `do`/`while` loop; anything else is rendered as labelled statements with gotos.
+fn decompile(program: &[i32]) -> String {
+    let mut output = String::new();
+    if let Some(shift) = loop_shift(program) {
+        output.push_str(&format!(
+            "// A is shifted right by {} bits per iteration.\n",
+            shift
+        ));
+        output.push_str("do {\n");
+        for instruction in program.chunks_exact(2).take(program.len() / 2 - 1) {
+            output.push_str(&format!(
+                "    {}\n",
+                statement(instruction[0], instruction[1])
+            ));
+        }
+        output.push_str("} while A != 0\n");
+    } else {
+        for (i, instruction) in program.chunks(2).enumerate() {
+            let line = match *instruction {
+                [opcode, operand] => statement(opcode, operand),
+                _ => format!("??? {}", instruction[0]),
+            };
+            output.push_str(&format!("{:>2}: {}\n", 2 * i, line));
+        }
+    }
+    output
+}
+
+#[cfg(feature = "z3")]
 struct SymbolicVm<'ctx> {
     context: &'ctx Context,
     ip: i32,
@@ -102,14 +427,15 @@ struct SymbolicVm<'ctx> {
     solver: Optimize<'ctx>,
 }
 
+#[cfg(feature = "z3")]
 impl<'ctx> SymbolicVm<'ctx> {
-    fn new(ctx: &'ctx Context, b: i32, c: i32, program: Vec<i32>) -> Self {
+    fn new(ctx: &'ctx Context, b: i64, c: i64, program: Vec<i32>) -> Self {
         SymbolicVm {
             context: ctx,
             ip: 0,
             a: BV::new_const(ctx, "a", 128),
-            b: BV::from_i64(ctx, b as i64, 128),
-            c: BV::from_i64(ctx, c as i64, 128),
+            b: BV::from_i64(ctx, b, 128),
+            c: BV::from_i64(ctx, c, 128),
             program,
             solver: Optimize::new(ctx),
         }
@@ -119,29 +445,37 @@ impl<'ctx> SymbolicVm<'ctx> {
         BV::from_i64(self.context, operand as i64, 128)
     }
 
-    fn combo(&self, operand: i32) -> BV<'ctx> {
+    fn combo(&self, operand: i32) -> Result<BV<'ctx>, String> {
         match operand {
-            0..=3 => self.literal(operand),
-            4 => self.a.clone(),
-            5 => self.b.clone(),
-            6 => self.c.clone(),
-            _ => unreachable!(),
+            0..=3 => Ok(self.literal(operand)),
+            4 => Ok(self.a.clone()),
+            5 => Ok(self.b.clone()),
+            6 => Ok(self.c.clone()),
+            _ => Err(format!(
+                "Invalid combo operand {} at ip={}",
+                operand, self.ip
+            )),
         }
     }
 
-    fn run(&mut self) -> String {
+    fn run(&mut self) -> Result<i64, String> {
         let start_a = self.a.clone();
         let mut i = 0;
-        loop {
-            if self.ip < 0 || self.ip >= self.program.len() as i32 {
-                break;
+        let mut cycles = 0;
+        while self.ip >= 0 && self.ip + 1 < self.program.len() as i32 {
+            if cycles == SYMBOLIC_CYCLES {
+                return Err(format!(
+                    "Program did not halt within {} cycles",
+                    SYMBOLIC_CYCLES
+                ));
             }
+            cycles += 1;
             let opcode = self.program[self.ip as usize];
             let operand = self.program[self.ip as usize + 1];
             match opcode {
-                /*adv*/ 0 => self.a = self.a.bvlshr(&self.combo(operand)),
+                /*adv*/ 0 => self.a = self.a.bvlshr(&self.combo(operand)?),
                 /*bxl*/ 1 => self.b = self.b.bvxor(&self.literal(operand)),
-                /*bst*/ 2 => self.b = self.combo(operand).bvand(&self.literal(7)),
+                /*bst*/ 2 => self.b = self.combo(operand)?.bvand(&self.literal(7)),
                 /*jnz*/
                 3 => {
                     if i == self.program.len() {
@@ -155,31 +489,86 @@ impl<'ctx> SymbolicVm<'ctx> {
                 /*out*/
                 5 => {
                     if i < self.program.len() {
-                        let lhs = self.combo(operand).bvand(&self.literal(7));
+                        let lhs = self.combo(operand)?.bvand(&self.literal(7));
                         let rhs = self.literal(self.program[i]);
                         self.solver.assert(&lhs._eq(&rhs));
                         i += 1;
                     }
                 }
-                /*bdv*/ 6 => self.b = self.a.bvlshr(&self.combo(operand)),
-                /*cdv*/ 7 => self.c = self.a.bvlshr(&self.combo(operand)),
-                _ => unreachable!(),
+                /*bdv*/ 6 => self.b = self.a.bvlshr(&self.combo(operand)?),
+                /*cdv*/ 7 => self.c = self.a.bvlshr(&self.combo(operand)?),
+                _ => return Err(format!("Invalid opcode {} at ip={}", opcode, self.ip)),
             }
             self.ip += 2;
         }
         self.solver.minimize(&start_a);
-        self.solver.check(&[]);
+        if self.solver.check(&[]) != z3::SatResult::Sat {
+            return Err("No value of A reproduces the program".to_string());
+        }
         let model = self.solver.get_model().unwrap();
         model
             .eval(&start_a, false)
-            .unwrap()
-            .as_u64()
-            .unwrap()
-            .to_string()
+            .and_then(|a| a.as_u64())
+            .and_then(|a| i64::try_from(a).ok())
+            .ok_or_else(|| "The value of A that reproduces the program is too large".to_string())
     }
 }
 
 impl Day {
+    /// Finds the smallest value of A for which the program outputs itself, or explains why it
+    /// couldn't be found. Programs the octal search can't solve are first tried with every small
+    /// value of A, and only then handed to the symbolic solver.
+    fn lowest_quine(&self) -> Result<i64, String> {
+        if is_octal_loop(&self.program)
+            && let Some(a) = find_quine(self.b, self.c, &self.program)
+        {
+            return Ok(a);
+        }
+        match brute_force_quine(self.b, self.c, &self.program, BRUTE_FORCE_LIMIT) {
+            Some(a) => Ok(a),
+            None => self.solve_symbolically(),
+        }
+    }
+
+    #[cfg(feature = "z3")]
+    fn solve_symbolically(&self) -> Result<i64, String> {
+        let ctx = Context::new(&z3::Config::default());
+        let mut vm = SymbolicVm::new(&ctx, self.b, self.c, self.program.clone());
+        vm.run()
+    }
+
+    #[cfg(not(feature = "z3"))]
+    fn solve_symbolically(&self) -> Result<i64, String> {
+        Err(format!(
+            "No value of A below {} reproduces the program (build with the z3 feature to search \
+             further)",
+            BRUTE_FORCE_LIMIT
+        ))
+    }
+
+    /// Replays the program concretely with the given value of A, stopping at every `out`
+    /// instruction to report the registers, and checks whether the program reproduced itself.
+    fn replay(&self, a: i64) -> String {
+        let breakpoints = (0..self.program.len() as i32)
+            .step_by(2)
+            .filter(|&ip| self.program[ip as usize] == 5)
+            .collect::<Vec<_>>();
+        let mut vm = ClassicVm::new(a, self.b, self.c, self.program.clone());
+        let mut lines = Vec::new();
+        loop {
+            match vm.resume(&breakpoints, CYCLE_BUDGET) {
+                Ok(Stop::Breakpoint(ip)) => lines.push(format!(
+                    "ip={:>2} A={} B={} C={} output={:?}",
+                    ip, vm.a, vm.b, vm.c, vm.output
+                )),
+                Ok(Stop::Halted) => break,
+                Err(e) => return e,
+            }
+        }
+        lines.push(format!("Reproduces program: {}", vm.output == self.program));
+        lines.join("\n")
+    }
+
     pub fn create(input: &str) -> Box<dyn Puzzle> {
         let (_, a) = regex_captures!(r#"Register A: (\d+)"#, input).unwrap();
         let (_, b) = regex_captures!(r#"Register B: (\d+)"#, input).unwrap();
@@ -234,10 +623,292 @@ mod tests {
         assert_eq!(puzzle.solve_part_2(), "117440");
     }
 
+    #[test]
+    fn test_trace() {
+        let mut vm = ClassicVm::new(10, 0, 0, vec![5, 0, 5, 1, 5, 4]);
+        let trace = vm.trace(CYCLE_BUDGET).unwrap();
+        assert_eq!(trace.len(), 3);
+        assert_eq!(
+            trace[2],
+            TraceStep {
+                ip: 4,
+                opcode: 5,
+                operand: 4,
+                a: 10,
+                b: 0,
+                c: 0,
+            }
+        );
+        assert_eq!(trace[2].to_string(), "ip= 4 out 4 A=10 B=0 C=0");
+        assert_eq!(vm.output, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_cycle_budget() {
+        let mut vm = ClassicVm::new(1, 0, 0, vec![3, 0]);
+        assert!(vm.run().is_err());
+        let mut vm = ClassicVm::new(1, 0, 0, vec![3, 0]);
+        assert!(vm.trace(100).is_err());
+        let mut vm = ClassicVm::new(729, 0, 0, vec![0, 1, 5, 4, 3, 0]);
+        assert!(vm.trace(10).is_err());
+    }
+
+    #[test]
+    fn test_divide() {
+        // adv A: A is divided by 2 to the power of itself.
+        let mut vm = ClassicVm::new(1 << 32, 0, 0, vec![0, 4]);
+        assert_eq!(vm.run(), Ok(vec![]));
+        assert_eq!(vm.a, 0);
+        let mut vm = ClassicVm::new(100, 0, 63, vec![0, 6]);
+        vm.run().unwrap();
+        assert_eq!(vm.a, 0);
+        let mut vm = ClassicVm::new(100, 0, 2, vec![0, 6]);
+        vm.run().unwrap();
+        assert_eq!(vm.a, 25);
+        let mut vm = ClassicVm::new(100, -1, 0, vec![0, 5]);
+        assert_eq!(
+            vm.run(),
+            Err("Cannot divide by 2 to the power of -1 at ip=0".to_string())
+        );
+        let mut vm = ClassicVm::new(100, 0, 0, vec![5, 7]);
+        assert_eq!(vm.run(), Err("Invalid combo operand 7 at ip=0".to_string()));
+        let mut vm = ClassicVm::new(100, 0, 0, vec![5, 0, 8, 0]);
+        assert_eq!(
+            vm.trace(CYCLE_BUDGET),
+            Err("Invalid opcode 8 at ip=2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut vm = ClassicVm::new(729, 0, 0, vec![0, 1, 5, 4, 3, 0]);
+        assert_eq!(vm.resume(&[2], CYCLE_BUDGET), Ok(Stop::Breakpoint(2)));
+        assert_eq!((vm.a, vm.output.clone()), (364, vec![]));
+        assert_eq!(vm.resume(&[2], CYCLE_BUDGET), Ok(Stop::Breakpoint(2)));
+        assert_eq!((vm.a, vm.output.clone()), (182, vec![4]));
+        assert_eq!(vm.resume(&[], CYCLE_BUDGET), Ok(Stop::Halted));
+        assert_eq!(vm.output, vec![4, 6, 3, 5, 6, 3, 5, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_replay_part_2_example_1() {
+        let input = "\
+            Register A: 2024\n\
+            Register B: 0\n\
+            Register C: 0\n\
+            \n\
+            Program: 0,3,5,4,3,0";
+        let puzzle = Day::create(input);
+        assert!(
+            puzzle
+                .inspect("replay")
+                .unwrap()
+                .ends_with("Reproduces program: true")
+        );
+        let mut vm = ClassicVm::new(117440, 0, 0, vec![0, 3, 5, 4, 3, 0]);
+        assert_eq!(vm.run().unwrap(), vec![0, 3, 5, 4, 3, 0]);
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program = vec![2, 4, 1, 1, 7, 5, 0, 3, 4, 7, 1, 6, 5, 5, 3, 0];
+        assert_eq!(
+            disassemble(&program),
+            vec![
+                " 0: bst A",
+                " 2: bxl 1",
+                " 4: cdv B",
+                " 6: adv 3",
+                " 8: bxc",
+                "10: bxl 6",
+                "12: out B",
+                "14: jnz 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disassemble_unknown_opcodes() {
+        let program = vec![8, 1, 5, 4, -1, 0];
+        assert_eq!(
+            disassemble(&program),
+            vec![" 0: ??? 8", " 2: out A", " 4: ??? -1"]
+        );
+        assert_eq!(
+            decompile(&program).lines().collect::<Vec<_>>(),
+            vec![" 0: ??? 8", " 2: out(A % 8)", " 4: ??? -1"]
+        );
+    }
+
+    #[test]
+    fn test_incomplete_instructions() {
+        // An opcode without an operand halts the program instead of being executed.
+        let mut vm = ClassicVm::new(0, 0, 0, vec![5, 0, 5]);
+        assert_eq!(vm.run(), Ok(vec![0]));
+        let mut vm = ClassicVm::new(1, 0, 0, vec![3, 1]);
+        assert_eq!(vm.run(), Ok(vec![]));
+        let program = vec![0, 3, 5, 4, 3, 0, 5];
+        assert_eq!(loop_shift(&program), None);
+        assert_eq!(
+            disassemble(&program),
+            vec![" 0: adv 3", " 2: out A", " 4: jnz 0", " 6: ??? 5"]
+        );
+        assert_eq!(
+            decompile(&program).lines().collect::<Vec<_>>(),
+            vec![
+                " 0: A = A >> 3",
+                " 2: out(A % 8)",
+                " 4: if A != 0 goto 0",
+                " 6: ??? 5"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decompile_loop() {
+        let program = vec![0, 3, 5, 4, 3, 0];
+        assert_eq!(loop_shift(&program), Some(3));
+        assert_eq!(
+            decompile(&program).lines().collect::<Vec<_>>(),
+            vec![
+                "// A is shifted right by 3 bits per iteration.",
+                "do {",
+                "    A = A >> 3",
+                "    out(A % 8)",
+                "} while A != 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decompile_without_loop() {
+        let program = vec![5, 0, 5, 1, 5, 4];
+        assert_eq!(loop_shift(&program), None);
+        assert_eq!(
+            decompile(&program).lines().collect::<Vec<_>>(),
+            vec![" 0: out(0 % 8)", " 2: out(1 % 8)", " 4: out(A % 8)"]
+        );
+    }
+
+    #[test]
+    fn test_find_quine() {
+        let program = vec![0, 3, 5, 4, 3, 0];
+        assert!(is_octal_loop(&program));
+        assert_eq!(find_quine(0, 0, &program), Some(117440));
+    }
+
+    #[test]
+    fn test_find_quine_rejects_other_shapes() {
+        assert!(!is_octal_loop(&[0, 1, 5, 4, 3, 0]));
+        assert!(!is_octal_loop(&[0, 3, 5, 4, 5, 4, 3, 0]));
+        assert!(!is_octal_loop(&[5, 0, 5, 1, 5, 4]));
+        // B carries over from one iteration to the next.
+        assert!(!is_octal_loop(&[1, 1, 5, 5, 0, 3, 3, 0]));
+        assert!(!is_octal_loop(&[2, 4, 4, 0, 5, 5, 0, 3, 3, 0]));
+        assert!(is_octal_loop(&[2, 4, 7, 5, 4, 0, 5, 5, 0, 3, 3, 0]));
+    }
+
+    #[test]
+    fn test_part_2_falls_back_when_the_octal_search_fails() {
+        // An octal loop whose only output is always 1, so it can never reproduce itself.
+        let program = [2, 4, 1, 7, 2, 1, 5, 5, 0, 3, 3, 0];
+        assert!(is_octal_loop(&program));
+        assert_eq!(find_quine(0, 0, &program), None);
+        let input = "\
+            Register A: 0\n\
+            Register B: 0\n\
+            Register C: 0\n\
+            \n\
+            Program: 2,4,1,7,2,1,5,5,0,3,3,0";
+        let puzzle = Day::create(input);
+        assert!(puzzle.solve_part_2().contains("reproduces the program"));
+        assert!(
+            puzzle
+                .inspect("replay")
+                .unwrap()
+                .contains("reproduces the program")
+        );
+    }
+
+    #[test]
+    fn test_brute_force_quine() {
+        let program = [0, 3, 5, 4, 3, 0];
+        assert_eq!(brute_force_quine(0, 0, &program, 100_000), None);
+        assert_eq!(brute_force_quine(0, 0, &program, 200_000), Some(117440));
+        // Programs that never halt are given up on.
+        assert_eq!(brute_force_quine(0, 0, &[3, 0], 10), None);
+    }
+
+    #[cfg(not(feature = "z3"))]
+    #[test]
+    fn test_part_2_unsupported_program() {
+        let input = "\
+            Register A: 2024\n\
+            Register B: 0\n\
+            Register C: 0\n\
+            \n\
+            Program: 0,1,5,4,3,0";
+        let puzzle = Day::create(input);
+        let expected = "No value of A below 65536 reproduces the program (build with the z3 \
+                        feature to search further)";
+        assert_eq!(puzzle.solve_part_2(), expected);
+        assert_eq!(puzzle.inspect("replay").unwrap(), expected);
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_solvers_agree() {
+        let ctx = Context::new(&z3::Config::default());
+        for program in [vec![0, 3, 5, 4, 3, 0], vec![2, 4, 1, 3, 5, 5, 0, 3, 3, 0]] {
+            let mut vm = SymbolicVm::new(&ctx, 0, 0, program.clone());
+            let native = find_quine(0, 0, &program).unwrap();
+            assert_eq!(vm.run(), Ok(native));
+        }
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_symbolic_errors() {
+        let ctx = Context::new(&z3::Config::default());
+        let run = |program: Vec<i32>| SymbolicVm::new(&ctx, 0, 0, program).run();
+        assert_eq!(
+            run(vec![3, 0]),
+            Err("Program did not halt within 10000 cycles".to_string())
+        );
+        assert_eq!(
+            run(vec![5, 7, 3, 0]),
+            Err("Invalid combo operand 7 at ip=0".to_string())
+        );
+        assert_eq!(
+            run(vec![8, 0, 3, 0]),
+            Err("Invalid opcode 8 at ip=0".to_string())
+        );
+    }
+
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/17")).unwrap();
         let puzzle = Day::create(&input);
         assert_eq!(puzzle.solve_part_2(), "107413700225434");
+        assert!(
+            puzzle
+                .inspect("replay")
+                .unwrap()
+                .ends_with("Reproduces program: true")
+        );
+    }
+
+    #[cfg(feature = "z3")]
+    #[test]
+    fn test_solve_part_2_symbolic() {
+        let input = std::fs::read_to_string(PathBuf::from("resources/tests/17")).unwrap();
+        let (_, program) = regex_captures!(r#"Program: (.*)"#, &input).unwrap();
+        let program = program
+            .split(",")
+            .map(|s| s.trim().parse().unwrap())
+            .collect::<Vec<i32>>();
+        let ctx = Context::new(&z3::Config::default());
+        let mut vm = SymbolicVm::new(&ctx, 0, 0, program);
+        assert_eq!(vm.run(), Ok(107413700225434));
     }
 }
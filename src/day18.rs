@@ -1,9 +1,15 @@
-use crate::puzzle::Puzzle;
-use petgraph::Graph;
-use petgraph::prelude::NodeIndex;
+use crate::grid::{Grid, Point};
+use crate::puzzle::{Puzzle, PuzzleParams};
+use crate::search::astar;
+use petgraph::unionfind::UnionFind;
 
 pub struct Day {
     bytes: Vec<(usize, usize)>,
+    size: usize,
+    /// The number of bytes fallen in part 1, or why there aren't that many bytes.
+    fallen: Result<usize, String>,
+    /// Why the bytes don't fit in the memory space, if they don't.
+    error: Option<String>,
 }
 
 impl Puzzle for Day {
@@ -12,25 +18,37 @@ impl Puzzle for Day {
     /// Time complexity: TODO
     /// Auxiliary space complexity: TODO
     fn solve_part_1(&self) -> String {
-        let mut memory = Memory::new(70, self.bytes.clone());
-        memory.apply_bytes(1024);
-        memory.find_shortest_path().unwrap().to_string()
+        let fallen = match (&self.error, &self.fallen) {
+            (Some(error), _) | (None, Err(error)) => return error.clone(),
+            (None, Ok(fallen)) => *fallen,
+        };
+        let mut memory = Memory::new(self.size, self.bytes.clone());
+        memory.apply_bytes(fallen);
+        match memory.find_shortest_path() {
+            Some(steps) => steps.to_string(),
+            None => "The exit can't be reached".to_string(),
+        }
     }
 
-    /// TODO
+    /// We're asked for the first byte that cuts the start off from the exit.
     ///
-    /// Time complexity: TODO
-    /// Auxiliary space complexity: TODO
+    /// Time complexity: O(n*α(n)) where n is the number of cells
+    /// Auxiliary space complexity: O(n)
     fn solve_part_2(&self) -> String {
-        let mut memory = Memory::new(70, self.bytes.clone());
-        let (x, y) = memory.find_first_break();
-        format!("{},{}", x, y)
+        if let Some(error) = &self.error {
+            return error.clone();
+        }
+        let memory = Memory::new(self.size, self.bytes.clone());
+        match memory.find_first_break() {
+            Some((x, y)) => format!("{},{}", x, y),
+            None => "No byte cuts off the exit".to_string(),
+        }
     }
 }
 
 struct Memory {
     size: usize,
-    graph: Vec<Vec<bool>>,
+    graph: Grid<bool>,
     bytes: Vec<(usize, usize)>,
     applied: usize,
 }
@@ -47,11 +65,24 @@ fn parse_input(input: &str) -> Vec<(usize, usize)> {
         .collect()
 }
 
+/// Bytes are given as (x, y) coordinates, i.e. (column, row).
+fn byte_point(byte: (usize, usize)) -> Point {
+    Point::new(byte.1 as i32, byte.0 as i32)
+}
+
+fn join_open_neighbors(open: &Grid<bool>, union_find: &mut UnionFind<usize>, point: Point) {
+    for neighbor in open.neighbors4(point) {
+        if open[neighbor] {
+            union_find.union(open.index(point), open.index(neighbor));
+        }
+    }
+}
+
 impl Memory {
     fn new(size: usize, bytes: Vec<(usize, usize)>) -> Self {
         Self {
             size,
-            graph: vec![vec![true; size + 1]; size + 1],
+            graph: Grid::new(size + 1, size + 1, true),
             bytes,
             applied: 0,
         }
@@ -60,89 +91,107 @@ impl Memory {
     fn apply_bytes(&mut self, new_applied: usize) {
         while self.applied < new_applied {
             let byte = self.bytes[self.applied];
-            self.graph[byte.0][byte.1] = false;
+            self.graph[byte_point(byte)] = false;
             self.applied += 1;
         }
         while self.applied > new_applied {
             let byte = self.bytes[self.applied - 1];
-            self.graph[byte.0][byte.1] = true;
+            self.graph[byte_point(byte)] = true;
             self.applied -= 1;
         }
     }
 
-    fn find_first_break(&mut self) -> (usize, usize) {
-        let mut low = 0;
-        let mut high = self.bytes.len();
-        while low < high {
-            let mid = (low + high) / 2;
-            self.apply_bytes(mid);
-            if let Some(_) = self.find_shortest_path() {
-                low = mid + 1;
-            } else {
-                high = mid;
-            }
-        }
-        self.bytes[high - 1]
+    /// Returns the first byte whose fall cuts the start off from the end, or None if none does.
+    fn find_first_break(&self) -> Option<(usize, usize)> {
+        let time = self.separation_time()?;
+        Some(self.bytes[time - 1])
     }
 
-    fn find_shortest_path(&self) -> Option<usize> {
-        let mut graph = Graph::<(usize, usize), usize>::default();
-
-        // Add nodes
-        let mut nodes = vec![vec![NodeIndex::new(0); self.size + 1]; self.size + 1];
-        for i in 0..=self.size {
-            for j in 0..=self.size {
-                if self.graph[i][j] {
-                    nodes[i][j] = graph.add_node((i, j));
-                }
-            }
+    /// Returns the number of fallen bytes at which the start and the end first become separated,
+    /// or None if they stay connected after every byte has fallen.
+    ///
+    /// Rather than re-pathing after each byte, we let every byte fall and then lift them back up in
+    /// reverse order, merging each reopened cell with its open neighbors in a union-find. The first
+    /// byte whose removal reconnects the start and the end is the one that separated them.
+    fn separation_time(&self) -> Option<usize> {
+        // A cell is blocked from the first time a byte lands on it.
+        let mut fall_time = self.graph.map(|_| None);
+        for (time, &byte) in self.bytes.iter().enumerate() {
+            fall_time[byte_point(byte)].get_or_insert(time);
+        }
+        let mut open = fall_time.map(|time| time.is_none());
+        let mut union_find: UnionFind<usize> = UnionFind::new(open.height() * open.width());
+        for point in open.positions(|&open| open) {
+            join_open_neighbors(&open, &mut union_find, point);
         }
 
-        // Add edges
-        for i in 0..=self.size {
-            for j in 0..=self.size {
-                if self.graph[i][j] {
-                    if i > 0 && self.graph[i - 1][j] {
-                        graph.add_edge(nodes[i][j], nodes[i - 1][j], 1);
-                    }
-                    if j > 0 && self.graph[i][j - 1] {
-                        graph.add_edge(nodes[i][j], nodes[i][j - 1], 1);
-                    }
-                    if i < self.size && self.graph[i + 1][j] {
-                        graph.add_edge(nodes[i][j], nodes[i + 1][j], 1);
-                    }
-                    if j < self.size && self.graph[i][j + 1] {
-                        graph.add_edge(nodes[i][j], nodes[i][j + 1], 1);
-                    }
-                }
+        let start = open.index(Point::new(0, 0));
+        let end = open.index(Point::new(self.size as i32, self.size as i32));
+        if union_find.equiv(start, end) {
+            return None;
+        }
+        for time in (0..self.bytes.len()).rev() {
+            let point = byte_point(self.bytes[time]);
+            if fall_time[point] != Some(time) {
+                continue; // Another byte already landed here earlier
+            }
+            open[point] = true;
+            join_open_neighbors(&open, &mut union_find, point);
+            if union_find.equiv(start, end) {
+                return Some(time + 1);
             }
         }
+        None
+    }
 
-        // Find the path
-        let start = nodes[0][0];
-        let end = nodes[self.size][self.size];
-        let path = petgraph::algo::astar(
-            &graph,
-            start,
-            |finish| finish == end,
-            |e| *e.weight(),
-            |n| {
-                let &(i, j) = graph.node_weight(n).unwrap();
-                2 * self.size - i - j
-            },
-        );
-        if let Some((distance, _)) = path {
-            Some(distance)
-        } else {
-            None
+    fn find_shortest_path(&self) -> Option<usize> {
+        let grid = &self.graph;
+        let start = Point::new(0, 0);
+        let end = Point::new(self.size as i32, self.size as i32);
+        if !grid[start] {
+            return None;
         }
+        let successors = |point: Point| {
+            grid.neighbors4(point)
+                .filter(move |&neighbor| grid[neighbor])
+                .map(|neighbor| (neighbor, 1))
+        };
+        astar(
+            start,
+            successors,
+            |point| point.manhattan_distance(end) as usize,
+            |point| point == end,
+        )
+        .map(|(distance, _)| distance)
     }
 }
 
 impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
+    /// Takes the parameters `size` (the largest coordinate, 70 by default) and `bytes` (the number
+    /// of bytes fallen in part 1, 1024 by default).
+    pub fn create(input: &str, params: &PuzzleParams) -> Box<dyn Puzzle> {
+        let bytes = parse_input(input);
+        let size = params.get("size", 70);
+        let fallen = params.get("bytes", 1024);
+        let error = bytes
+            .iter()
+            .find(|&&(x, y)| x > size || y > size)
+            .map(|&(x, y)| {
+                format!(
+                    "Byte {},{} falls outside the memory space, whose largest coordinate is {}",
+                    x, y, size
+                )
+            });
+        let fallen = if fallen <= bytes.len() {
+            Ok(fallen)
+        } else {
+            Err(format!("Only {} bytes fall, not {}", bytes.len(), fallen))
+        };
         Box::new(Day {
-            bytes: parse_input(input),
+            bytes,
+            size,
+            fallen,
+            error,
         })
     }
 }
@@ -150,6 +199,7 @@ impl Day {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::iproduct;
     use std::path::PathBuf;
 
     #[test]
@@ -184,12 +234,15 @@ mod tests {
         let mut memory = Memory::new(6, bytes);
         memory.apply_bytes(12);
         assert_eq!(memory.find_shortest_path(), Some(22));
+        let params = PuzzleParams::default().with("size", 6).with("bytes", 12);
+        let puzzle = Day::create(input, &params);
+        assert_eq!(puzzle.solve_part_1(), "22");
     }
 
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/18")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "384");
     }
 
@@ -222,14 +275,104 @@ mod tests {
             1,6\n\
             2,0";
         let bytes = parse_input(input);
-        let mut memory = Memory::new(6, bytes);
-        assert_eq!(memory.find_first_break(), (6, 1));
+        let memory = Memory::new(6, bytes);
+        assert_eq!(memory.find_first_break(), Some((6, 1)));
+        let params = PuzzleParams::default().with("size", 6);
+        let puzzle = Day::create(input, &params);
+        assert_eq!(puzzle.solve_part_2(), "6,1");
+    }
+
+    #[test]
+    fn test_separation_time() {
+        let memory = Memory::new(2, vec![(1, 0), (1, 1), (2, 0), (0, 2), (1, 2)]);
+        assert_eq!(memory.separation_time(), Some(4));
+        assert_eq!(memory.find_first_break(), Some((0, 2)));
+
+        // A byte landing twice on the same cell doesn't reopen it.
+        let memory = Memory::new(2, vec![(1, 0), (1, 0), (1, 1), (1, 2)]);
+        assert_eq!(memory.separation_time(), Some(4));
+
+        let memory = Memory::new(2, vec![(1, 1), (2, 0)]);
+        assert_eq!(memory.separation_time(), None);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let input = "5,4\n4,2\n4,5";
+        let params = PuzzleParams::default().with("size", 6).with("bytes", 4);
+        let puzzle = Day::create(input, &params);
+        assert!(params.take_errors().is_empty());
+        assert_eq!(puzzle.solve_part_1(), "Only 3 bytes fall, not 4");
+        assert_eq!(puzzle.solve_part_2(), "No byte cuts off the exit");
+
+        let params = PuzzleParams::default().with("size", 4).with("bytes", 3);
+        let puzzle = Day::create(input, &params);
+        let expected = "Byte 5,4 falls outside the memory space, whose largest coordinate is 4";
+        assert_eq!(puzzle.solve_part_1(), expected);
+        assert_eq!(puzzle.solve_part_2(), expected);
+
+        let params = PuzzleParams::default().with("size", "big").with("bytes", 3);
+        Day::create(input, &params);
+        assert_eq!(
+            params.take_errors(),
+            vec!["Invalid value for parameter size: big (invalid digit found in string)"]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_exit() {
+        let params = PuzzleParams::default().with("size", 1).with("bytes", 1);
+        let puzzle = Day::create("1,0\n0,1", &params);
+        assert_eq!(puzzle.solve_part_1(), "2");
+        assert_eq!(puzzle.solve_part_2(), "0,1");
+
+        let params = PuzzleParams::default().with("size", 1).with("bytes", 2);
+        let puzzle = Day::create("1,0\n0,1", &params);
+        assert_eq!(puzzle.solve_part_1(), "The exit can't be reached");
+
+        let params = PuzzleParams::default().with("size", 2).with("bytes", 1);
+        let puzzle = Day::create("1,1", &params);
+        assert_eq!(puzzle.solve_part_2(), "No byte cuts off the exit");
+    }
+
+    /// Finds the first break the slow way, by binary searching on the number of fallen bytes.
+    fn find_first_break_by_search(memory: &mut Memory) -> Option<(usize, usize)> {
+        let mut low = 0;
+        let mut high = memory.bytes.len() + 1;
+        while low < high {
+            let mid = (low + high) / 2;
+            memory.apply_bytes(mid.min(memory.bytes.len()));
+            if mid <= memory.bytes.len() && memory.find_shortest_path().is_some() {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        (high <= memory.bytes.len()).then(|| memory.bytes[high - 1])
+    }
+
+    #[test]
+    fn test_separation_time_matches_search() {
+        use rand::seq::SliceRandom;
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(18);
+        for _ in 0..200 {
+            // Real inputs never drop two bytes on the same cell, which the search relies on.
+            let size = rng.random_range(1..8);
+            let mut bytes = iproduct!(0..=size, 0..=size).collect::<Vec<_>>();
+            bytes.shuffle(&mut rng);
+            bytes.truncate(rng.random_range(0..bytes.len()));
+            let mut memory = Memory::new(size, bytes.clone());
+            let expected = find_first_break_by_search(&mut memory);
+            let time = memory.separation_time();
+            assert_eq!(time.map(|time| bytes[time - 1]), expected, "{:?}", bytes);
+        }
     }
 
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/18")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "36,10");
     }
 }
@@ -1,12 +1,11 @@
-use crate::puzzle::Puzzle;
-use itertools::iproduct;
-use petgraph::algo::dijkstra;
-use petgraph::graph::DiGraph;
-use petgraph::prelude::NodeIndex;
+use crate::grid::{Grid, Point};
+use crate::puzzle::{Puzzle, PuzzleParams};
+use crate::search::distance_field;
 use std::collections::HashMap;
 
 pub struct Day {
     racetrack: Racetrack,
+    threshold: usize,
 }
 
 impl Puzzle for Day {
@@ -18,7 +17,13 @@ impl Puzzle for Day {
         self.racetrack
             .find_cheats(2)
             .iter()
-            .filter_map(|(&delta, &count)| if delta >= 100 { Some(count) } else { None })
+            .filter_map(|(&delta, &count)| {
+                if delta >= self.threshold {
+                    Some(count)
+                } else {
+                    None
+                }
+            })
             .sum::<usize>()
             .to_string()
     }
@@ -31,127 +36,50 @@ impl Puzzle for Day {
         self.racetrack
             .find_cheats(20)
             .iter()
-            .filter_map(|(&delta, &count)| if delta >= 100 { Some(count) } else { None })
+            .filter_map(|(&delta, &count)| {
+                if delta >= self.threshold {
+                    Some(count)
+                } else {
+                    None
+                }
+            })
             .sum::<usize>()
             .to_string()
     }
 }
 
 struct Racetrack {
-    start: (usize, usize),
-    end: (usize, usize),
-    grid: Vec<Vec<bool>>,
+    start: Point,
+    end: Point,
+    grid: Grid<bool>,
 }
 
 impl Racetrack {
     fn create(input: &str) -> Self {
-        let mut start = (0, 0);
-        let mut end = (0, 0);
-        let grid: Vec<Vec<bool>> = input
-            .lines()
-            .enumerate()
-            .map(|(row, line)| {
-                line.chars()
-                    .enumerate()
-                    .map(|(col, ch)| {
-                        match ch {
-                            '#' => false, // Not traversable
-                            '.' => true,  // Traversable
-                            'S' => {
-                                start = (row, col);
-                                true // Start is traversable
-                            }
-                            'E' => {
-                                end = (row, col);
-                                true // End is traversable
-                            }
-                            _ => unreachable!(),
-                        }
-                    })
-                    .collect()
-            })
-            .collect();
-        Racetrack { start, end, grid }
-    }
-
-    fn num_rows(&self) -> usize {
-        self.grid.len()
-    }
-
-    fn num_cols(&self) -> usize {
-        self.grid[0].len()
-    }
-
-    fn neighbors(&self, row: usize, col: usize) -> Vec<(usize, usize)> {
-        let mut neighbors = Vec::with_capacity(4);
-        if row > 0 {
-            neighbors.push((row - 1, col));
-        }
-        if col > 0 {
-            neighbors.push((row, col - 1));
+        let grid = Grid::parse(input);
+        let start = grid.find('S').unwrap();
+        let end = grid.find('E').unwrap();
+        Racetrack {
+            start,
+            end,
+            grid: grid.map(|&ch| ch != '#'),
         }
-        if row < self.num_rows() - 1 {
-            neighbors.push((row + 1, col));
-        }
-        if col < self.num_cols() - 1 {
-            neighbors.push((row, col + 1));
-        }
-        neighbors
-    }
-
-    fn distance_map(&self, start: (usize, usize)) -> Vec<Vec<usize>> {
-        let mut graph = DiGraph::<(usize, usize), usize>::default();
-        let mut indices = vec![vec![NodeIndex::new(0); self.num_cols()]; self.num_rows()];
-
-        // Add nodes
-        for (a, b) in iproduct!(0..self.num_rows(), 0..self.num_cols()) {
-            if self.grid[a][b] {
-                indices[a][b] = graph.add_node((a, b));
-            }
-        }
-
-        // Add edges
-        for (a, b) in iproduct!(0..self.num_rows(), 0..self.num_cols()) {
-            if !self.grid[a][b] {
-                continue;
-            }
-            for (c, d) in self.neighbors(a, b) {
-                if self.grid[c][d] {
-                    graph.add_edge(indices[a][b], indices[c][d], 1);
-                }
-            }
-        }
-
-        let start = indices[start.0][start.1];
-        let result = dijkstra(&graph, start, None, |e| *e.weight());
-        let mut distances = vec![vec![usize::MAX; self.num_cols()]; self.num_rows()];
-        for (&node, &dist) in result.iter() {
-            let &(row, col) = graph.node_weight(node).unwrap();
-            distances[row][col] = dist;
-        }
-        distances
-    }
-
-    fn manhatten_distance(&self, a: (usize, usize), b: (usize, usize)) -> usize {
-        (a.0 as isize - b.0 as isize).abs() as usize + (a.1 as isize - b.1 as isize).abs() as usize
     }
 
     fn find_cheats(&self, cheat_time: usize) -> HashMap<usize, usize> {
-        let start_distances = self.distance_map(self.start);
-        let end_distances = self.distance_map(self.end);
-        let honorable_distance = start_distances[self.end.0][self.end.1];
+        let start_distances = distance_field(&self.grid, self.start, |&open| open);
+        let end_distances = distance_field(&self.grid, self.end, |&open| open);
+        let honorable_distance = start_distances[self.end].unwrap();
         let mut deltas: HashMap<usize, usize> = HashMap::new();
-        for (a, b) in iproduct!(0..self.num_rows(), 0..self.num_cols()) {
-            let start_to_cheat_distance = start_distances[a][b];
-            if start_to_cheat_distance == usize::MAX {
+        for (a, &start_to_cheat_distance) in start_distances.iter() {
+            let Some(start_to_cheat_distance) = start_to_cheat_distance else {
                 continue;
-            }
-            for (c, d) in iproduct!(0..self.num_rows(), 0..self.num_cols()) {
-                let cheat_to_end_distance = end_distances[c][d];
-                if cheat_to_end_distance == usize::MAX {
+            };
+            for (b, &cheat_to_end_distance) in end_distances.iter() {
+                let Some(cheat_to_end_distance) = cheat_to_end_distance else {
                     continue;
-                }
-                let cheat_distance = self.manhatten_distance((a, b), (c, d));
+                };
+                let cheat_distance = a.manhattan_distance(b) as usize;
                 if cheat_distance > cheat_time {
                     continue;
                 }
@@ -168,9 +96,15 @@ impl Racetrack {
 }
 
 impl Day {
-    pub fn create(input: &str) -> Box<dyn Puzzle> {
+    /// Takes the parameter `threshold` (the minimum number of picoseconds a cheat must save to be
+    /// counted, 100 by default).
+    pub fn create(input: &str, params: &PuzzleParams) -> Box<dyn Puzzle> {
         let racetrack = Racetrack::create(input);
-        Box::new(Day { racetrack })
+        let threshold = params.get("threshold", 100);
+        Box::new(Day {
+            racetrack,
+            threshold,
+        })
     }
 }
 
@@ -210,12 +144,14 @@ mod tests {
         assert_eq!(*deltas.get(&38).unwrap(), 1);
         assert_eq!(*deltas.get(&40).unwrap(), 1);
         assert_eq!(*deltas.get(&64).unwrap(), 1);
+        let puzzle = Day::create(input, &PuzzleParams::default().with("threshold", 20));
+        assert_eq!(puzzle.solve_part_1(), "5");
     }
 
     #[test]
     fn test_solve_part_1() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/20")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_1(), "1409");
     }
 
@@ -253,12 +189,14 @@ mod tests {
         assert_eq!(*deltas.get(&72).unwrap(), 22);
         assert_eq!(*deltas.get(&74).unwrap(), 4);
         assert_eq!(*deltas.get(&76).unwrap(), 3);
+        let puzzle = Day::create(input, &PuzzleParams::default().with("threshold", 50));
+        assert_eq!(puzzle.solve_part_2(), "285");
     }
 
     #[test]
     fn test_solve_part_2() {
         let input = std::fs::read_to_string(PathBuf::from("resources/tests/20")).unwrap();
-        let puzzle = Day::create(&input);
+        let puzzle = Day::create(&input, &PuzzleParams::default());
         assert_eq!(puzzle.solve_part_2(), "1012821");
     }
 }
@@ -0,0 +1,175 @@
+use crate::puzzle::Puzzle;
+use itertools::iproduct;
+
+pub struct Day {
+    locks: Vec<[u8; 5]>,
+    keys: Vec<[u8; 5]>,
+}
+
+impl Puzzle for Day {
+    /// We're given a set of lock and key schematics and asked to count the number of lock/key pairs
+    /// whose pin and key heights don't overlap in any column.
+    ///
+    /// Time complexity: O(l*k)
+    /// Auxiliary space complexity: O(1)
+    fn solve_part_1(&self) -> String {
+        iproduct!(self.locks.iter(), self.keys.iter())
+            .filter(|&(lock, key)| fits(lock, key))
+            .count()
+            .to_string()
+    }
+
+    /// There is no second puzzle on the last day.
+    ///
+    /// Time complexity: O(1)
+    /// Auxiliary space complexity: O(1)
+    fn solve_part_2(&self) -> String {
+        "Merry Christmas!".to_string()
+    }
+}
+
+const HEIGHT: u8 = 5;
+
+fn fits(lock: &[u8; 5], key: &[u8; 5]) -> bool {
+    lock.iter().zip(key.iter()).all(|(l, k)| l + k <= HEIGHT)
+}
+
+fn parse_heights(schematic: &[Vec<char>]) -> [u8; 5] {
+    let mut heights = [0; 5];
+    for row in &schematic[1..schematic.len() - 1] {
+        for (col, &ch) in row.iter().enumerate() {
+            if ch == '#' {
+                heights[col] += 1;
+            }
+        }
+    }
+    heights
+}
+
+impl Day {
+    pub fn create(input: &str) -> Box<dyn Puzzle> {
+        let mut locks = Vec::new();
+        let mut keys = Vec::new();
+        for block in input.split("\n\n") {
+            let schematic = block
+                .lines()
+                .map(|line| line.trim().chars().collect::<Vec<_>>())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>();
+            // Blank lines around the schematics, e.g. at the end of the input, leave empty blocks.
+            if schematic.is_empty() {
+                continue;
+            }
+            let heights = parse_heights(&schematic);
+            if schematic[0].iter().all(|&ch| ch == '#') {
+                locks.push(heights);
+            } else {
+                keys.push(heights);
+            }
+        }
+        Box::new(Day { locks, keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part_1_example_1() {
+        let input = "\
+            #####\n\
+            .####\n\
+            .####\n\
+            .####\n\
+            .#.#.\n\
+            .#...\n\
+            .....\n\
+            \n\
+            #####\n\
+            ##.##\n\
+            .#.##\n\
+            ...##\n\
+            ...#.\n\
+            ...#.\n\
+            .....\n\
+            \n\
+            .....\n\
+            #....\n\
+            #....\n\
+            #...#\n\
+            #.#.#\n\
+            #.###\n\
+            #####\n\
+            \n\
+            .....\n\
+            .....\n\
+            #.#..\n\
+            ###..\n\
+            ###.#\n\
+            ###.#\n\
+            #####\n\
+            \n\
+            .....\n\
+            .....\n\
+            .....\n\
+            #....\n\
+            #.#..\n\
+            #.#.#\n\
+            #####";
+        let puzzle = Day::create(input);
+        assert_eq!(puzzle.solve_part_1(), "3");
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        let input = "\
+            \n\
+            #####\n\
+            .####\n\
+            .####\n\
+            .####\n\
+            .#.#.\n\
+            .#...\n\
+            .....\n\
+            \n\
+            \n\
+            .....\n\
+            #....\n\
+            #....\n\
+            #...#\n\
+            #.#.#\n\
+            #.###\n\
+            #####\n\
+            \n";
+        let puzzle = Day::create(input);
+        assert_eq!(puzzle.solve_part_1(), "0");
+    }
+
+    #[test]
+    fn test_parse_heights() {
+        let lock = "\
+            #####\n\
+            .####\n\
+            .####\n\
+            .####\n\
+            .#.#.\n\
+            .#...\n\
+            .....";
+        let key = "\
+            .....\n\
+            #....\n\
+            #....\n\
+            #...#\n\
+            #.#.#\n\
+            #.###\n\
+            #####";
+        let parse = |s: &str| {
+            s.lines()
+                .map(|line| line.chars().collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(parse_heights(&parse(lock)), [0, 5, 3, 4, 3]);
+        assert_eq!(parse_heights(&parse(key)), [5, 0, 2, 1, 3]);
+    }
+}
@@ -0,0 +1,382 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::str::FromStr;
+
+/// A location on a grid. Rows grow downwards and columns grow to the right. Coordinates are signed
+/// so that stepping off the edge of a grid produces a point that is simply out of bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point {
+    pub row: i32,
+    pub col: i32,
+}
+
+impl Point {
+    pub const fn new(row: i32, col: i32) -> Self {
+        Self { row, col }
+    }
+
+    /// Returns the point one step away in the given direction.
+    pub fn step(self, dir: Direction) -> Self {
+        let (drow, dcol) = dir.delta();
+        Self::new(self.row + drow, self.col + dcol)
+    }
+
+    pub fn manhattan_distance(self, other: Point) -> u32 {
+        self.row.abs_diff(other.row) + self.col.abs_diff(other.col)
+    }
+}
+
+/// One of the eight compass directions, listed clockwise starting from North.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// The four orthogonal directions, clockwise starting from North.
+    pub const CARDINAL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    /// All eight directions, clockwise starting from North.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// Returns the (row, col) offset of a single step in this direction.
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Direction::North => (-1, 0),
+            Direction::NorthEast => (-1, 1),
+            Direction::East => (0, 1),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (1, 0),
+            Direction::SouthWest => (1, -1),
+            Direction::West => (0, -1),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+
+    /// Returns the direction rotated 90 degrees clockwise.
+    pub fn clockwise(self) -> Self {
+        Self::ALL[(self as usize + 2) % 8]
+    }
+
+    /// Returns the direction rotated 90 degrees counter-clockwise.
+    pub fn counter_clockwise(self) -> Self {
+        Self::ALL[(self as usize + 6) % 8]
+    }
+
+    /// Returns the opposite direction.
+    pub fn reverse(self) -> Self {
+        Self::ALL[(self as usize + 4) % 8]
+    }
+
+    /// Parses one of the arrow characters `^`, `>`, `v` and `<`.
+    pub fn from_arrow(c: char) -> Option<Self> {
+        match c {
+            '^' => Some(Direction::North),
+            '>' => Some(Direction::East),
+            'v' => Some(Direction::South),
+            '<' => Some(Direction::West),
+            _ => None,
+        }
+    }
+
+    /// Returns the arrow character for an orthogonal direction, the inverse of `from_arrow`.
+    pub fn arrow(self) -> Option<char> {
+        match self {
+            Direction::North => Some('^'),
+            Direction::East => Some('>'),
+            Direction::South => Some('v'),
+            Direction::West => Some('<'),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a direction from its name (e.g. `north` or `SouthWest`, ignoring case) or its arrow.
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next())
+            && let Some(dir) = Direction::from_arrow(c)
+        {
+            return Ok(dir);
+        }
+        Direction::ALL
+            .into_iter()
+            .find(|dir| format!("{:?}", dir).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("Unknown direction: {}", s))
+    }
+}
+
+/// A rectangular grid stored as a flat vector in row-major order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Grid<T> {
+    height: usize,
+    width: usize,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Creates a grid with the given dimensions, with every cell set to the given value.
+    pub fn new(height: usize, width: usize, value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self {
+            height,
+            width,
+            cells: vec![value; height * width],
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn in_bounds(&self, point: Point) -> bool {
+        point.row >= 0
+            && (point.row as usize) < self.height
+            && point.col >= 0
+            && (point.col as usize) < self.width
+    }
+
+    /// Returns the cell at the given point, or None if the point is out of bounds.
+    pub fn get(&self, point: Point) -> Option<&T> {
+        if self.in_bounds(point) {
+            Some(&self.cells[self.index(point)])
+        } else {
+            None
+        }
+    }
+
+    /// Returns the position of the point in the row-major cell vector.
+    pub fn index(&self, point: Point) -> usize {
+        point.row as usize * self.width + point.col as usize
+    }
+
+    /// Returns every point in the grid in row-major order.
+    pub fn points(&self) -> impl Iterator<Item = Point> + use<T> {
+        let width = self.width;
+        (0..self.height * self.width)
+            .map(move |index| Point::new((index / width) as i32, (index % width) as i32))
+    }
+
+    /// Returns every point in the grid along with its cell, in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.points().zip(self.cells.iter())
+    }
+
+    /// Returns the orthogonal neighbors of the point that are within the grid.
+    pub fn neighbors4(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
+        Direction::CARDINAL
+            .into_iter()
+            .map(move |dir| point.step(dir))
+            .filter(|&neighbor| self.in_bounds(neighbor))
+    }
+
+    /// Returns the orthogonal and diagonal neighbors of the point that are within the grid.
+    pub fn neighbors8(&self, point: Point) -> impl Iterator<Item = Point> + '_ {
+        Direction::ALL
+            .into_iter()
+            .map(move |dir| point.step(dir))
+            .filter(|&neighbor| self.in_bounds(neighbor))
+    }
+
+    /// Returns the points of every cell matching the predicate, in row-major order.
+    pub fn positions<'a>(
+        &'a self,
+        mut f: impl FnMut(&T) -> bool + 'a,
+    ) -> impl Iterator<Item = Point> + 'a {
+        self.iter()
+            .filter(move |(_, cell)| f(cell))
+            .map(|(point, _)| point)
+    }
+
+    /// Creates a new grid of the same dimensions by applying the function to every cell.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+        Grid {
+            height: self.height,
+            width: self.width,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// Returns the grid mirrored along its main diagonal, so rows become columns.
+    #[allow(dead_code)]
+    pub fn transpose(&self) -> Self
+    where
+        T: Clone,
+    {
+        let cells = (0..self.width)
+            .flat_map(|col| (0..self.height).map(move |row| (row, col)))
+            .map(|(row, col)| self.cells[row * self.width + col].clone())
+            .collect();
+        Self {
+            height: self.width,
+            width: self.height,
+            cells,
+        }
+    }
+}
+
+impl Grid<char> {
+    /// Parses a grid with one row per line. Leading and trailing whitespace on each line is
+    /// ignored, as are blank lines.
+    pub fn parse(input: &str) -> Self {
+        let rows = input
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+        let height = rows.len();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        let cells = rows.iter().flat_map(|row| row.chars()).collect::<Vec<_>>();
+        assert_eq!(
+            cells.len(),
+            height * width,
+            "Grid rows must be the same width"
+        );
+        Self {
+            height,
+            width,
+            cells,
+        }
+    }
+
+    /// Returns the first point (in row-major order) containing the given character.
+    pub fn find(&self, c: char) -> Option<Point> {
+        self.positions(|&cell| cell == c).next()
+    }
+}
+
+impl<T> Index<Point> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, point: Point) -> &T {
+        assert!(self.in_bounds(point), "{:?} is out of bounds", point);
+        &self.cells[Grid::index(self, point)]
+    }
+}
+
+impl<T> IndexMut<Point> for Grid<T> {
+    fn index_mut(&mut self, point: Point) -> &mut T {
+        assert!(self.in_bounds(point), "{:?} is out of bounds", point);
+        let index = Grid::index(self, point);
+        &mut self.cells[index]
+    }
+}
+
+/// Renders the grid back to text, one line per row.
+impl fmt::Display for Grid<char> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (row, cells) in self.cells.chunks(self.width.max(1)).enumerate() {
+            if row > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", cells.iter().collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render() {
+        let input = "\
+            #.#\n\
+            .S.\n\
+            ##E";
+        let grid = Grid::parse(input);
+        assert_eq!(grid.height(), 3);
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid[Point::new(1, 1)], 'S');
+        assert_eq!(grid.find('E'), Some(Point::new(2, 2)));
+        assert_eq!(grid.find('X'), None);
+        assert_eq!(grid.to_string(), "#.#\n.S.\n##E");
+    }
+
+    #[test]
+    fn test_bounds() {
+        let mut grid = Grid::new(2, 3, 0);
+        assert!(grid.in_bounds(Point::new(1, 2)));
+        assert!(!grid.in_bounds(Point::new(2, 0)));
+        assert!(!grid.in_bounds(Point::new(0, -1)));
+        assert_eq!(grid.get(Point::new(-1, 0)), None);
+        grid[Point::new(1, 2)] = 5;
+        grid[Point::new(0, 0)] = 1;
+        assert_eq!(grid.get(Point::new(1, 2)), Some(&5));
+        assert_eq!(grid.positions(|&cell| cell > 0).count(), 2);
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let grid = Grid::new(3, 3, ());
+        let corner = grid.neighbors4(Point::new(0, 0)).collect::<Vec<_>>();
+        assert_eq!(corner, vec![Point::new(0, 1), Point::new(1, 0)]);
+        assert_eq!(grid.neighbors4(Point::new(1, 1)).count(), 4);
+        assert_eq!(grid.neighbors8(Point::new(0, 0)).count(), 3);
+        assert_eq!(grid.neighbors8(Point::new(1, 1)).count(), 8);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let grid = Grid::parse("abc\ndef");
+        let transposed = grid.transpose();
+        assert_eq!(transposed.height(), 3);
+        assert_eq!(transposed.width(), 2);
+        assert_eq!(transposed.to_string(), "ad\nbe\ncf");
+        assert_eq!(transposed.transpose(), grid);
+    }
+
+    #[test]
+    fn test_directions() {
+        for dir in Direction::ALL {
+            assert_eq!(dir.clockwise().counter_clockwise(), dir);
+            assert_eq!(dir.clockwise().clockwise(), dir.reverse());
+            let (drow, dcol) = dir.delta();
+            assert_eq!(dir.reverse().delta(), (-drow, -dcol));
+        }
+        assert_eq!(Direction::North.clockwise(), Direction::East);
+        assert_eq!(Direction::from_arrow('<'), Some(Direction::West));
+        for dir in Direction::CARDINAL {
+            assert_eq!(Direction::from_arrow(dir.arrow().unwrap()), Some(dir));
+        }
+        assert_eq!(Direction::NorthEast.arrow(), None);
+        assert_eq!("north".parse(), Ok(Direction::North));
+        assert_eq!("SouthWest".parse(), Ok(Direction::SouthWest));
+        assert_eq!("v".parse(), Ok(Direction::South));
+        assert!("up".parse::<Direction>().is_err());
+        let point = Point::new(2, 3).step(Direction::SouthWest);
+        assert_eq!(point, Point::new(3, 2));
+        assert_eq!(point.manhattan_distance(Point::new(0, 0)), 5);
+    }
+}
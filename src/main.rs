@@ -1,5 +1,5 @@
 use crate::input_fetcher::InputFetcher;
-use crate::puzzle::Puzzle;
+use crate::puzzle::{Puzzle, PuzzleParams};
 
 mod day01;
 mod day02;
@@ -21,35 +21,76 @@ mod day17;
 mod day18;
 mod day19;
 mod day20;
+mod day25;
+mod grid;
 mod input_fetcher;
+mod number_theory;
 mod puzzle;
+mod search;
+
+type Constructor = fn(&str, &PuzzleParams) -> Box<dyn Puzzle>;
 
 fn main() {
     let fetcher = InputFetcher::create();
-    let puzzles: Vec<Box<dyn Puzzle>> = vec![
-        day01::Day::create(fetcher.get_input(1).unwrap().as_str()),
-        day02::Day::create(fetcher.get_input(2).unwrap().as_str()),
-        day03::Day::create(fetcher.get_input(3).unwrap().as_str()),
-        day04::Day::create(fetcher.get_input(4).unwrap().as_str()),
-        day05::Day::create(fetcher.get_input(5).unwrap().as_str()),
-        day06::Day::create(fetcher.get_input(6).unwrap().as_str()),
-        day07::Day::create(fetcher.get_input(7).unwrap().as_str()),
-        day08::Day::create(fetcher.get_input(8).unwrap().as_str()),
-        day09::Day::create(fetcher.get_input(9).unwrap().as_str()),
-        day10::Day::create(fetcher.get_input(10).unwrap().as_str()),
-        day11::Day::create(fetcher.get_input(11).unwrap().as_str()),
-        day12::Day::create(fetcher.get_input(12).unwrap().as_str()),
-        day13::Day::create(fetcher.get_input(13).unwrap().as_str()),
-        day14::Day::create(fetcher.get_input(14).unwrap().as_str()),
-        day15::Day::create(fetcher.get_input(15).unwrap().as_str()),
-        day16::Day::create(fetcher.get_input(16).unwrap().as_str()),
-        day17::Day::create(fetcher.get_input(17).unwrap().as_str()),
-        day18::Day::create(fetcher.get_input(18).unwrap().as_str()),
-        day19::Day::create(fetcher.get_input(19).unwrap().as_str()),
-        day20::Day::create(fetcher.get_input(20).unwrap().as_str()),
+    let puzzles: Vec<(u8, Constructor)> = vec![
+        (1, |input, _| day01::Day::create(input)),
+        (2, |input, _| day02::Day::create(input)),
+        (3, |input, _| day03::Day::create(input)),
+        (4, |input, _| day04::Day::create(input)),
+        (5, |input, _| day05::Day::create(input)),
+        (6, |input, _| day06::Day::create(input)),
+        (7, day07::Day::create),
+        (8, |input, _| day08::Day::create(input)),
+        (9, |input, _| day09::Day::create(input)),
+        (10, day10::Day::create),
+        (11, |input, _| day11::Day::create(input)),
+        (12, |input, _| day12::Day::create(input)),
+        (13, |input, _| day13::Day::create(input)),
+        (14, day14::Day::create),
+        (15, day15::Day::create),
+        (16, day16::Day::create),
+        (17, |input, _| day17::Day::create(input)),
+        (18, day18::Day::create),
+        (19, |input, _| day19::Day::create(input)),
+        (20, day20::Day::create),
+        (25, |input, _| day25::Day::create(input)),
     ];
-    for (i, puzzle) in puzzles.iter().enumerate() {
-        println!("Day {:02} Part 1: {}", i + 1, puzzle.solve_part_1());
-        println!("Day {:02} Part 2: {}", i + 1, puzzle.solve_part_2());
+    let mut args = std::env::args().skip(1);
+    let selected_day = args
+        .next()
+        .map(|day| day.parse::<u8>().expect("Day must be a number"));
+    let mut mode = None;
+    let mut params = PuzzleParams::default();
+    for arg in args {
+        match arg.split_once('=') {
+            Some((name, value)) => params = params.with(name, value),
+            None => mode = Some(arg),
+        }
+    }
+    for (day, create) in puzzles {
+        if selected_day.is_some_and(|selected_day| selected_day != day) {
+            continue;
+        }
+        let puzzle = create(fetcher.get_input(day).unwrap().as_str(), &params);
+        for name in params.unused() {
+            eprintln!("Day {:02} does not take parameter '{}'", day, name);
+        }
+        let errors = params.take_errors();
+        if !errors.is_empty() {
+            for error in errors {
+                eprintln!("Day {:02}: {}", day, error);
+            }
+            continue;
+        }
+        match &mode {
+            Some(mode) => match puzzle.inspect(mode) {
+                Some(output) => println!("{}", output),
+                None => eprintln!("Day {:02} does not support mode '{}'", day, mode),
+            },
+            None => {
+                println!("Day {:02} Part 1: {}", day, puzzle.solve_part_1());
+                println!("Day {:02} Part 2: {}", day, puzzle.solve_part_2());
+            }
+        }
     }
 }
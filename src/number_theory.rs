@@ -0,0 +1,69 @@
+/// Returns the greatest common divisor of a and b.
+pub fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+/// Returns (g, x, y) such that a*x + b*y = g = gcd(a, b).
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+pub fn lcm(a: u64, b: u64) -> u64 {
+    a / extended_gcd(a as i128, b as i128).0 as u64 * b
+}
+
+/// Finds the smallest t such that t = a1 (mod m1) and t = a2 (mod m2), if there is one.
+pub fn crt(a1: u64, m1: u64, a2: u64, m2: u64) -> Option<u64> {
+    let (a1, m1, a2, m2) = (a1 as i128, m1 as i128, a2 as i128, m2 as i128);
+    let (g, inverse, _) = extended_gcd(m1, m2);
+    if (a2 - a1) % g != 0 {
+        return None;
+    }
+    // t = a1 + m1*k where m1*k = a2 - a1 (mod m2), so k = (a2 - a1)/g * (m1/g)^-1 (mod m2/g).
+    let modulus = m2 / g;
+    let k = ((a2 - a1) / g * inverse).rem_euclid(modulus);
+    Some((a1 + m1 * k).rem_euclid(m1 * modulus) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-4, 6), 2);
+        assert_eq!(gcd(0, 5), 5);
+        assert_eq!(gcd(7, 0), 7);
+    }
+
+    #[test]
+    fn test_extended_gcd() {
+        assert_eq!(extended_gcd(240, 46).0, 2);
+        let (g, x, y) = extended_gcd(94, 34);
+        assert_eq!((g, 94 * x + 34 * y), (2, 2));
+        assert_eq!(extended_gcd(7, 0), (7, 1, 0));
+    }
+
+    #[test]
+    fn test_lcm() {
+        assert_eq!(lcm(4, 6), 12);
+        assert_eq!(lcm(101, 103), 10403);
+    }
+
+    #[test]
+    fn test_crt() {
+        assert_eq!(crt(2, 3, 3, 5), Some(8));
+        assert_eq!(crt(0, 101, 0, 103), Some(0));
+        assert_eq!(crt(3, 4, 1, 6), Some(7));
+        assert_eq!(crt(0, 4, 1, 6), None);
+        for t in 0..101 * 103 {
+            assert_eq!(crt(t % 101, 101, t % 103, 103), Some(t));
+        }
+    }
+}
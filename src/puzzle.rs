@@ -1,4 +1,97 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::str::FromStr;
+
 pub trait Puzzle {
     fn solve_part_1(&self) -> String;
     fn solve_part_2(&self) -> String;
+
+    /// Returns a human-readable view of the puzzle for the given debugging mode, or None if the
+    /// day doesn't support that mode.
+    fn inspect(&self, _mode: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Named parameters that override the constants a puzzle is normally solved with (e.g. the size
+/// of its grid), so that the smaller examples can be run through the same code as the real input.
+#[derive(Debug, Default)]
+pub struct PuzzleParams {
+    values: HashMap<String, String>,
+    used: RefCell<HashSet<String>>,
+    errors: RefCell<Vec<String>>,
+}
+
+impl PuzzleParams {
+    /// Returns the parameters with the given parameter set to the value.
+    pub fn with(mut self, name: &str, value: impl ToString) -> Self {
+        self.values.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Returns the value of the parameter, or the default if it wasn't set or is invalid.
+    pub fn get<T: FromStr<Err: Display>>(&self, name: &str, default: T) -> T {
+        self.get_optional(name).unwrap_or(default)
+    }
+
+    /// Returns the value of the parameter, or None if it wasn't set. A value that can't be parsed
+    /// is treated as unset and recorded as an error, see [`PuzzleParams::take_errors`].
+    pub fn get_optional<T: FromStr<Err: Display>>(&self, name: &str) -> Option<T> {
+        self.used.borrow_mut().insert(name.to_string());
+        let value = self.values.get(name)?;
+        match value.parse() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.errors.borrow_mut().push(format!(
+                    "Invalid value for parameter {}: {} ({})",
+                    name, value, e
+                ));
+                None
+            }
+        }
+    }
+
+    /// Returns the errors from the parameters read since the last call, clearing them.
+    pub fn take_errors(&self) -> Vec<String> {
+        self.errors.take()
+    }
+
+    /// Returns the names of the parameters that were set but never read, in sorted order.
+    pub fn unused(&self) -> Vec<&str> {
+        let used = self.used.borrow();
+        let mut unused = self
+            .values
+            .keys()
+            .filter(|name| !used.contains(*name))
+            .map(|name| name.as_str())
+            .collect::<Vec<_>>();
+        unused.sort();
+        unused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        let params = PuzzleParams::default().with("size", 6).with("typo", 1);
+        assert_eq!(params.get("size", 70), 6);
+        assert_eq!(params.get("bytes", 1024), 1024);
+        assert_eq!(params.get_optional::<u32>("bytes"), None);
+        assert_eq!(params.unused(), vec!["typo"]);
+    }
+
+    #[test]
+    fn test_invalid_param() {
+        let params = PuzzleParams::default().with("size", "big");
+        assert_eq!(params.get("size", 70), 70);
+        assert_eq!(
+            params.take_errors(),
+            vec!["Invalid value for parameter size: big (invalid digit found in string)"]
+        );
+        assert!(params.take_errors().is_empty());
+    }
 }
@@ -0,0 +1,300 @@
+use crate::grid::{Grid, Point};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+/// Computes the number of orthogonal steps from the start to every cell of the grid, only moving
+/// through cells that are passable. Cells that can't be reached are None.
+pub fn distance_field<T>(
+    grid: &Grid<T>,
+    start: Point,
+    passable: impl Fn(&T) -> bool,
+) -> Grid<Option<usize>> {
+    let mut distances = grid.map(|_| None);
+    distances[start] = Some(0);
+    let mut queue = VecDeque::from([start]);
+    while let Some(point) = queue.pop_front() {
+        let distance = distances[point].unwrap();
+        for neighbor in grid.neighbors4(point) {
+            if distances[neighbor].is_none() && passable(&grid[neighbor]) {
+                distances[neighbor] = Some(distance + 1);
+                queue.push_back(neighbor);
+            }
+        }
+    }
+    distances
+}
+
+/// The result of a Dijkstra search: the cost of the cheapest path to every reachable node, along
+/// with every predecessor of each node that lies on one of its cheapest paths.
+pub struct ShortestPaths<N> {
+    pub distances: HashMap<N, usize>,
+    pub predecessors: HashMap<N, Vec<N>>,
+}
+
+impl<N: Copy + Eq + Hash> ShortestPaths<N> {
+    /// Returns every node that lies on at least one cheapest path to any of the targets.
+    pub fn nodes_on_paths_to(&self, targets: impl IntoIterator<Item = N>) -> HashSet<N> {
+        let mut seen = HashSet::new();
+        let mut stack = targets
+            .into_iter()
+            .filter(|target| self.distances.contains_key(target))
+            .collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            if seen.insert(node)
+                && let Some(previous) = self.predecessors.get(&node)
+            {
+                stack.extend(previous);
+            }
+        }
+        seen
+    }
+
+    /// Returns up to `limit` of the cheapest paths to the targets, each starting at a start node and
+    /// ending at one of the targets. Paths never visit a node twice, even when zero cost edges
+    /// would allow it.
+    pub fn paths_to(&self, targets: impl IntoIterator<Item = N>, limit: usize) -> Vec<Vec<N>> {
+        let mut paths = Vec::new();
+        // Partial paths are built backwards from the targets, following the predecessors.
+        let mut stack = targets
+            .into_iter()
+            .filter(|target| self.distances.contains_key(target))
+            .map(|target| vec![target])
+            .collect::<Vec<_>>();
+        while paths.len() < limit
+            && let Some(mut path) = stack.pop()
+        {
+            match self.predecessors.get(path.last().unwrap()) {
+                Some(previous) => {
+                    for &node in previous.iter().filter(|node| !path.contains(node)) {
+                        let mut next = path.clone();
+                        next.push(node);
+                        stack.push(next);
+                    }
+                }
+                None => {
+                    path.reverse();
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+}
+
+/// Finds the cheapest paths from the start nodes to every reachable node, where the successors of
+/// a node (and the cost of moving to each) are given by a function rather than a materialized
+/// graph.
+pub fn dijkstra<N, I>(
+    starts: impl IntoIterator<Item = N>,
+    mut successors: impl FnMut(N) -> I,
+) -> ShortestPaths<N>
+where
+    N: Copy + Eq + Hash + Ord,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    let starts = starts.into_iter().collect::<HashSet<_>>();
+    let mut distances = HashMap::new();
+    let mut predecessors: HashMap<N, Vec<N>> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    for &start in &starts {
+        distances.insert(start, 0);
+        heap.push(Reverse((0, start)));
+    }
+    while let Some(Reverse((cost, node))) = heap.pop() {
+        if cost > distances[&node] {
+            continue; // A cheaper path to this node has already been processed
+        }
+        for (next, step) in successors(node) {
+            let next_cost = cost + step;
+            match distances.get(&next) {
+                Some(&best) if next_cost > best => {}
+                Some(&best) if next_cost == best => {
+                    // Zero cost edges can lead back to a start, but paths always begin there.
+                    if !starts.contains(&next) {
+                        predecessors.entry(next).or_default().push(node);
+                    }
+                }
+                _ => {
+                    distances.insert(next, next_cost);
+                    predecessors.insert(next, vec![node]);
+                    heap.push(Reverse((next_cost, next)));
+                }
+            }
+        }
+    }
+    ShortestPaths {
+        distances,
+        predecessors,
+    }
+}
+
+/// Finds the cheapest path from the start to a goal node, guided by a heuristic that must never
+/// overestimate the remaining cost. Returns the cost of the path along with the path itself.
+pub fn astar<N, I>(
+    start: N,
+    mut successors: impl FnMut(N) -> I,
+    mut heuristic: impl FnMut(N) -> usize,
+    mut is_goal: impl FnMut(N) -> bool,
+) -> Option<(usize, Vec<N>)>
+where
+    N: Copy + Eq + Hash + Ord,
+    I: IntoIterator<Item = (N, usize)>,
+{
+    let mut distances = HashMap::from([(start, 0)]);
+    let mut predecessors = HashMap::new();
+    let mut heap = BinaryHeap::from([Reverse((heuristic(start), 0, start))]);
+    while let Some(Reverse((_, cost, node))) = heap.pop() {
+        if cost > distances[&node] {
+            continue; // A cheaper path to this node has already been processed
+        }
+        if is_goal(node) {
+            let mut path = vec![node];
+            let mut current = node;
+            while let Some(&previous) = predecessors.get(&current) {
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some((cost, path));
+        }
+        for (next, step) in successors(node) {
+            let next_cost = cost + step;
+            if distances.get(&next).is_none_or(|&best| next_cost < best) {
+                distances.insert(next, next_cost);
+                predecessors.insert(next, node);
+                heap.push(Reverse((next_cost + heuristic(next), next_cost, next)));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze() -> Grid<char> {
+        Grid::parse(
+            "\
+            ..#.\n\
+            .##.\n\
+            ....\n\
+            #.#.",
+        )
+    }
+
+    fn successors(grid: &Grid<char>) -> impl FnMut(Point) -> Vec<(Point, usize)> + '_ {
+        move |point| {
+            grid.neighbors4(point)
+                .filter(|&neighbor| grid[neighbor] == '.')
+                .map(|neighbor| (neighbor, 1))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_distance_field() {
+        let grid = maze();
+        let distances = distance_field(&grid, Point::new(0, 0), |&ch| ch == '.');
+        assert_eq!(distances[Point::new(0, 0)], Some(0));
+        assert_eq!(distances[Point::new(2, 3)], Some(5));
+        assert_eq!(distances[Point::new(0, 3)], Some(7));
+        assert_eq!(distances[Point::new(0, 2)], None);
+        assert_eq!(distances[Point::new(3, 0)], None);
+    }
+
+    #[test]
+    fn test_dijkstra_matches_distance_field() {
+        let grid = maze();
+        let start = Point::new(0, 0);
+        let expected = distance_field(&grid, start, |&ch| ch == '.');
+        let paths = dijkstra([start], successors(&grid));
+        for point in grid.points() {
+            assert_eq!(paths.distances.get(&point).copied(), expected[point]);
+        }
+    }
+
+    #[test]
+    fn test_dijkstra_predecessors() {
+        let grid = Grid::parse(
+            "\
+            ...\n\
+            .#.\n\
+            ...",
+        );
+        let start = Point::new(0, 0);
+        let end = Point::new(2, 2);
+        let paths = dijkstra([start], successors(&grid));
+        assert_eq!(paths.distances[&end], 4);
+        assert_eq!(paths.predecessors[&end].len(), 2);
+        // Both ways around the wall are equally cheap, so every open cell is on a cheapest path.
+        assert_eq!(paths.nodes_on_paths_to([end]).len(), 8);
+        assert!(paths.nodes_on_paths_to([Point::new(1, 1)]).is_empty());
+        let mut routes = paths.paths_to([end], usize::MAX);
+        routes.sort();
+        assert_eq!(
+            routes,
+            vec![
+                vec![
+                    start,
+                    Point::new(0, 1),
+                    Point::new(0, 2),
+                    Point::new(1, 2),
+                    end
+                ],
+                vec![
+                    start,
+                    Point::new(1, 0),
+                    Point::new(2, 0),
+                    Point::new(2, 1),
+                    end
+                ],
+            ]
+        );
+        assert_eq!(paths.paths_to([end], 1).len(), 1);
+    }
+
+    #[test]
+    fn test_zero_cost_cycles() {
+        // Nodes 0 and 1 can swap back and forth for free, and both lead on to 2.
+        let successors = |node: u32| match node {
+            0 => vec![(1, 0), (2, 5)],
+            1 => vec![(0, 0), (2, 5)],
+            _ => vec![],
+        };
+        let paths = dijkstra([0], successors);
+        assert_eq!(paths.distances[&1], 0);
+        assert_eq!(paths.distances[&2], 5);
+        assert!(!paths.predecessors.contains_key(&0));
+        let mut routes = paths.paths_to([2], usize::MAX);
+        routes.sort();
+        assert_eq!(routes, vec![vec![0, 1, 2], vec![0, 2]]);
+    }
+
+    #[test]
+    fn test_astar() {
+        let grid = maze();
+        let goal = Point::new(0, 3);
+        let (cost, path) = astar(
+            Point::new(0, 0),
+            successors(&grid),
+            |point| point.manhattan_distance(goal) as usize,
+            |point| point == goal,
+        )
+        .unwrap();
+        assert_eq!(cost, 7);
+        assert_eq!(path.len(), 8);
+        assert!(
+            path.windows(2)
+                .all(|pair| pair[0].manhattan_distance(pair[1]) == 1)
+        );
+        let unreachable = astar(
+            Point::new(0, 0),
+            successors(&grid),
+            |_| 0,
+            |point| point == Point::new(3, 0),
+        );
+        assert_eq!(unreachable, None);
+    }
+}